@@ -3,17 +3,47 @@ extern crate criterion;
 extern crate aoc_2018;
 
 use aoc_2018::*;
-use criterion::Criterion;
-use std::collections::BTreeMap;
+use criterion::{Benchmark, Criterion};
+use std::rc::Rc;
 
 fn criterion_benchmark(c: &mut Criterion) {
-    // c.bench_function("day1", |b| b.iter(|| day1::run()));
-    // c.bench_function("day2", |b| b.iter(|| day2::run()));
-    // c.bench_function("day3", |b| b.iter(|| day3::run()));
-    // c.bench_function("day4", |b| b.iter(|| day4::run()));
-    // c.bench_function("day5", |b| b.iter(|| day5::run()));
-    // c.bench_function("day6", |b| b.iter(|| day6::run()));
-    c.bench_function("day7", |b| b.iter(|| day7::run()));
+    for day in CALENDAR.iter() {
+        let solution = match day.solution {
+            Some(solution) => solution,
+            None => continue,
+        };
+        let raw = match load_input(&day.input()) {
+            Ok(raw) => raw,
+            Err(err) => {
+                eprintln!("skipping day{}: {}", day.number, err);
+                continue;
+            }
+        };
+        let name = format!("day{}", day.number);
+        let input = Rc::new(
+            solution
+                .dyn_parse(&raw)
+                .expect("solved days parse their input"),
+        );
+
+        c.bench(
+            "parse",
+            Benchmark::new(name.clone(), move |b| b.iter(|| solution.dyn_parse(&raw))),
+        );
+
+        let parsed = input.clone();
+        c.bench(
+            "part_a",
+            Benchmark::new(name.clone(), move |b| {
+                b.iter(|| solution.dyn_part_a(&**parsed))
+            }),
+        );
+
+        c.bench(
+            "part_b",
+            Benchmark::new(name, move |b| b.iter(|| solution.dyn_part_b(&**input))),
+        );
+    }
 }
 
 criterion_group!(benches, criterion_benchmark);
@@ -0,0 +1,186 @@
+//! Known answers, keyed by day, input and part, used to catch regressions
+//!
+//! The answers are stored as tab-separated lines `day digest part answer` where `digest`
+//! identifies the puzzle input, which makes it possible to keep the answers for several
+//! personal inputs in the same file.
+
+use super::{parse_input, AocError, Result};
+use std::collections::BTreeMap;
+use std::fmt::{self, Display};
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+/// Default location of the answers, relative to the working directory
+pub const ANSWERS_FILE: &str = "answers.tsv";
+
+const HEADER: &str = "# day\tinput\tpart\tanswer";
+
+#[derive(Copy, Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Part {
+    A,
+    B,
+}
+
+impl Display for Part {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Part::A => write!(f, "a"),
+            Part::B => write!(f, "b"),
+        }
+    }
+}
+
+impl FromStr for Part {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "a" | "A" => Ok(Part::A),
+            "b" | "B" => Ok(Part::B),
+            _ => Err("expected part `a` or `b`"),
+        }
+    }
+}
+
+/// Outcome of comparing an answer against the known answer
+#[derive(Clone, Debug, PartialEq)]
+pub enum Verdict {
+    Pass,
+    Fail { expected: String },
+    Unknown,
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct Answers {
+    known: BTreeMap<(usize, String, Part), String>,
+}
+
+impl Answers {
+    /// Read the answers from `path`, a missing file is treated as no known answers
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        match parse_input(path) {
+            Ok(raw) => raw.parse(),
+            Err(AocError::MissingInput(_)) => Ok(Self::default()),
+            Err(err) => Err(err),
+        }
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        fs::write(path, self.to_string())?;
+        Ok(())
+    }
+
+    pub fn get(&self, day: usize, digest: &str, part: Part) -> Option<&str> {
+        self.known
+            .get(&(day, digest.to_string(), part))
+            .map(String::as_str)
+    }
+
+    /// Record `answer` and return the previously known answer, if any
+    pub fn record(&mut self, day: usize, digest: &str, part: Part, answer: &str) -> Option<String> {
+        self.known
+            .insert((day, digest.to_string(), part), answer.to_string())
+    }
+
+    pub fn check(&self, day: usize, digest: &str, part: Part, answer: &str) -> Verdict {
+        match self.get(day, digest, part) {
+            Some(expected) if expected == answer => Verdict::Pass,
+            Some(expected) => Verdict::Fail {
+                expected: expected.to_string(),
+            },
+            None => Verdict::Unknown,
+        }
+    }
+}
+
+impl FromStr for Answers {
+    type Err = AocError;
+
+    fn from_str(raw: &str) -> Result<Self> {
+        let mut answers = Self::default();
+
+        for (idx, line) in raw.lines().enumerate() {
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let parse_err = |reason| AocError::parse(idx + 1, line, reason);
+            let fields: Vec<&str> = line.splitn(4, '\t').collect();
+            if fields.len() != 4 {
+                return Err(parse_err("expected `day\tinput\tpart\tanswer`"));
+            }
+            let day = fields[0].parse().map_err(|_| parse_err("bad day"))?;
+            let part = fields[2].parse().map_err(parse_err)?;
+            answers.record(day, fields[1], part, fields[3]);
+        }
+
+        Ok(answers)
+    }
+}
+
+impl Display for Answers {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{}", HEADER)?;
+        for ((day, digest, part), answer) in &self.known {
+            writeln!(f, "{}\t{}\t{}\t{}", day, digest, part, answer)?;
+        }
+        Ok(())
+    }
+}
+
+/// Identifies a puzzle input by its contents (64-bit FNV-1a), ignoring trailing whitespace
+pub fn digest(input: &str) -> String {
+    const OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0100_0000_01b3;
+
+    let hash = input.trim_end().bytes().fold(OFFSET, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(PRIME)
+    });
+    format!("{:016x}", hash)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn roundtrip() {
+        let mut answers = Answers::default();
+        let digest = digest("+1\n-2\n");
+        assert_eq!(answers.record(1, &digest, Part::B, "2"), None);
+        assert_eq!(answers.record(1, &digest, Part::A, "-1"), None);
+        assert_eq!(
+            answers.record(1, &digest, Part::A, "-2"),
+            Some("-1".to_string())
+        );
+
+        let parsed: Answers = answers.to_string().parse().unwrap();
+        assert_eq!(parsed, answers);
+        assert_eq!(parsed.check(1, &digest, Part::A, "-2"), Verdict::Pass);
+        assert_eq!(
+            parsed.check(1, &digest, Part::B, "3"),
+            Verdict::Fail {
+                expected: "2".to_string()
+            }
+        );
+        assert_eq!(parsed.check(2, &digest, Part::A, "3"), Verdict::Unknown);
+    }
+
+    #[test]
+    fn digest_ignores_trailing_whitespace() {
+        assert_eq!(digest("abc\n"), digest("abc"));
+        assert_ne!(digest("abc"), digest("abd"));
+        assert_eq!(digest(""), "cbf29ce484222325");
+    }
+
+    #[test]
+    fn malformed() {
+        let err = "# day\tinput\tpart\tanswer\n1\tabc\tc\t5"
+            .parse::<Answers>()
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "line 2: expected part `a` or `b` `1\tabc\tc\t5`"
+        );
+    }
+}
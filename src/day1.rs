@@ -1,44 +1,402 @@
-use super::{parse_input, Output};
-use std::collections::HashSet;
+use super::{load_input, parse_lines, AocError, Output, Result, Solution};
+use std::collections::{BTreeMap, HashSet};
+use std::fmt::{self, Display};
 
 const INPUT: &str = "day1.txt";
 type Counter = isize;
 
-pub fn run() -> Output<Counter, Counter> {
-    let input: Vec<Counter> = parse_input(INPUT)
-        .lines()
-        .map(|line| line.parse().expect("well-formed integers"))
-        .collect();
-    Output {
-        a: part_a(&input),
-        b: part_b(&input),
+pub struct Day1;
+
+impl Solution for Day1 {
+    type Input = Vec<Counter>;
+    type A = Counter;
+    type B = Counter;
+
+    fn parse(&self, raw: &str) -> Result<Self::Input> {
+        parse_lines(raw)
+    }
+
+    fn part_a(&self, input: &Self::Input) -> Result<Self::A> {
+        Ok(part_a(input))
+    }
+
+    fn part_b(&self, input: &Self::Input) -> Result<Self::B> {
+        part_b(input).map_err(AocError::unsolvable)
+    }
+}
+
+/// Why no frequency is ever reached twice
+#[derive(Debug, PartialEq)]
+pub struct NoRepeat {
+    /// Change in frequency after one pass over the list
+    pub drift: Counter,
+    /// Number of distinct frequencies reached in one pass, including the start
+    pub distinct: usize,
+}
+
+impl Display for NoRepeat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.drift == 0 {
+            write!(f, "the list of frequency changes is empty")
+        } else {
+            write!(
+                f,
+                "frequency drifts by {} per pass and none of the {} distinct frequencies of a \
+                 pass are congruent modulo {}, so no frequency is reached twice",
+                self.drift,
+                self.distinct,
+                self.drift.abs()
+            )
+        }
+    }
+}
+
+/// When a frequency was first reached again
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Revisit {
+    /// Number of changes applied so far, including the one reaching the frequency
+    pub iteration: usize,
+    /// Number of full passes over the change list completed before it
+    pub pass: usize,
+}
+
+/// Applies frequency changes one at a time and keeps statistics which can be queried at any
+/// point, e.g. to follow what happens over several passes of the change list
+#[derive(Clone, Debug)]
+pub struct FrequencyDevice {
+    frequency: Counter,
+    min: Counter,
+    max: Counter,
+    iterations: usize,
+    passes: usize,
+    seen: HashSet<Counter>,
+    revisited: BTreeMap<Counter, Revisit>,
+    first_repeat: Option<Counter>,
+}
+
+impl Default for FrequencyDevice {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FrequencyDevice {
+    /// A device at frequency 0 which counts as reached once
+    pub fn new() -> Self {
+        let mut seen = HashSet::new();
+        seen.insert(0);
+        Self {
+            frequency: 0,
+            min: 0,
+            max: 0,
+            iterations: 0,
+            passes: 0,
+            seen,
+            revisited: BTreeMap::new(),
+            first_repeat: None,
+        }
+    }
+
+    /// Apply a single change, returns the revisit if this is the first time the new
+    /// frequency is reached again
+    pub fn apply(&mut self, change: Counter) -> Option<Revisit> {
+        self.frequency += change;
+        self.iterations += 1;
+        self.min = self.min.min(self.frequency);
+        self.max = self.max.max(self.frequency);
+
+        if self.seen.insert(self.frequency) || self.revisited.contains_key(&self.frequency) {
+            return None;
+        }
+        let revisit = Revisit {
+            iteration: self.iterations,
+            pass: self.passes,
+        };
+        self.revisited.insert(self.frequency, revisit);
+        self.first_repeat.get_or_insert(self.frequency);
+        Some(revisit)
+    }
+
+    /// Apply all `changes` in order and count them as one pass
+    pub fn apply_pass(&mut self, changes: &[Counter]) {
+        for &change in changes {
+            self.apply(change);
+        }
+        self.passes += 1;
+    }
+
+    pub fn frequency(&self) -> Counter {
+        self.frequency
+    }
+
+    /// Lowest frequency reached so far
+    pub fn min(&self) -> Counter {
+        self.min
+    }
+
+    /// Highest frequency reached so far
+    pub fn max(&self) -> Counter {
+        self.max
+    }
+
+    /// Number of changes applied
+    pub fn iterations(&self) -> usize {
+        self.iterations
+    }
+
+    /// Number of completed passes
+    pub fn passes(&self) -> usize {
+        self.passes
+    }
+
+    /// Number of distinct frequencies reached, including the start
+    pub fn distinct(&self) -> usize {
+        self.seen.len()
+    }
+
+    /// The first frequency reached twice and when that happened
+    pub fn first_repeat(&self) -> Option<(Counter, Revisit)> {
+        self.first_repeat
+            .map(|frequency| (frequency, self.revisited[&frequency]))
+    }
+
+    /// When `frequency` was first reached again, if it has been
+    pub fn revisit(&self, frequency: Counter) -> Option<Revisit> {
+        self.revisited.get(&frequency).copied()
+    }
+
+    /// Every frequency reached more than once, ordered by frequency
+    pub fn revisited(&self) -> &BTreeMap<Counter, Revisit> {
+        &self.revisited
     }
 }
 
+/// Solve both parts for the puzzle input `input`
+pub fn solve(input: &str) -> Result<Output<Counter, Counter>> {
+    Day1.solve(input)
+}
+
+pub fn run() -> Result<Output<Counter, Counter>> {
+    solve(&load_input(INPUT)?)
+}
+
 fn part_a(numbers: &[Counter]) -> Counter {
     numbers.iter().sum()
 }
 
-fn part_b(numbers: &[Counter]) -> Counter {
+/// First frequency reached twice or why no frequency repeats, O(n log n)
+///
+/// Pass `k` over the changes visits `prefix[i] + k * drift` where `prefix` are the frequencies
+/// of the first pass and `drift` the sum of all changes. So unless the first pass repeats
+/// itself, `prefix[i]` eventually hits `prefix[j]` iff they are congruent modulo `drift` and
+/// `prefix[j]` lies ahead of it in the direction of the drift. Only the closest such `prefix[j]`
+/// matters which is its neighbour after sorting each residue class.
+fn part_b(numbers: &[Counter]) -> std::result::Result<Counter, NoRepeat> {
+    let mut prefix = Vec::with_capacity(numbers.len());
+    let mut seen = HashSet::with_capacity(numbers.len());
     let mut current = 0;
-    let mut seen = HashSet::new();
-    seen.insert(current);
 
-    for n in numbers.iter().cycle() {
-        current += n;
+    for n in numbers {
         if !seen.insert(current) {
-            break;
+            return Ok(current);
         }
+        prefix.push(current);
+        current += n;
     }
-    current
+
+    let drift = current;
+    if numbers.is_empty() {
+        return Err(NoRepeat { drift, distinct: 1 });
+    } else if drift == 0 {
+        // the second pass starts at the first frequency again
+        return Ok(0);
+    }
+
+    // (residue, position along the drift, index in the pass)
+    let mut classes: Vec<(Counter, Counter, usize)> = prefix
+        .iter()
+        .enumerate()
+        .map(|(idx, &freq)| (freq.rem_euclid(drift), freq * drift.signum(), idx))
+        .collect();
+    classes.sort_unstable();
+
+    classes
+        .windows(2)
+        .filter(|pair| pair[0].0 == pair[1].0)
+        .map(|pair| {
+            let (_, behind, idx) = pair[0];
+            let (_, ahead, _) = pair[1];
+            let passes = ((ahead - behind) / drift.abs()) as usize;
+            let step = passes.saturating_mul(numbers.len()).saturating_add(idx);
+            (step, ahead * drift.signum())
+        })
+        .min()
+        .map(|(_step, freq)| freq)
+        .ok_or(NoRepeat {
+            drift,
+            distinct: prefix.len(),
+        })
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::Rng;
+
+    /// Straightforward simulation of at most `steps` changes
+    fn simulate(numbers: &[Counter], steps: usize) -> Option<Counter> {
+        let mut current = 0;
+        let mut seen = HashSet::new();
+        seen.insert(current);
+
+        for n in numbers.iter().cycle().take(steps) {
+            current += n;
+            if !seen.insert(current) {
+                return Some(current);
+            }
+        }
+        None
+    }
+
+    #[test]
+    fn b() {
+        assert_eq!(part_b(&[1, -1]), Ok(0));
+        assert_eq!(part_b(&[3, 3, 4, -2, -4]), Ok(10));
+        assert_eq!(part_b(&[-6, 3, 8, 5, -6]), Ok(5));
+        assert_eq!(part_b(&[7, 7, -2, -7, -4]), Ok(14));
+    }
+
+    #[test]
+    fn b_zero_drift() {
+        // the start is the only frequency reached twice
+        assert_eq!(part_b(&[2, -1, 3, -4]), Ok(0));
+        // repeats within the first pass win over the start
+        assert_eq!(part_b(&[2, -1, 1, -2]), Ok(2));
+    }
+
+    #[test]
+    fn b_positive_drift() {
+        assert_eq!(part_b(&[-2, 3]), Ok(0));
+        assert_eq!(part_b(&[5, -4, 3]), Ok(5));
+    }
+
+    #[test]
+    fn b_negative_drift() {
+        assert_eq!(part_b(&[-2, 1]), Ok(-2));
+        assert_eq!(part_b(&[-5, 4, -3]), Ok(-5));
+    }
+
+    #[test]
+    fn b_never_repeats() {
+        let err = part_b(&[1, 1]).unwrap_err();
+        assert_eq!(
+            err,
+            NoRepeat {
+                drift: 2,
+                distinct: 2
+            }
+        );
+        assert_eq!(
+            err.to_string(),
+            "frequency drifts by 2 per pass and none of the 2 distinct frequencies of a pass \
+             are congruent modulo 2, so no frequency is reached twice"
+        );
+        assert_eq!(
+            part_b(&[3, -1, 5]),
+            Err(NoRepeat {
+                drift: 7,
+                distinct: 3
+            })
+        );
+        assert_eq!(
+            part_b(&[]).unwrap_err().to_string(),
+            "the list of frequency changes is empty"
+        );
+        assert!(solve("+1\n+1\n").is_err());
+    }
+
+    #[test]
+    fn b_matches_simulation() {
+        let mut rng = Rng::new(0x2018);
+
+        for _ in 0..500 {
+            let len = 1 + rng.below(20);
+            let numbers: Vec<Counter> = (0..len).map(|_| rng.below(21) as Counter - 10).collect();
+            // the first pass spans at most 200 so any repeat happens within 201 passes
+            assert_eq!(
+                part_b(&numbers).ok(),
+                simulate(&numbers, 202 * len),
+                "{:?}",
+                numbers
+            );
+        }
+    }
+
+    #[test]
+    fn device() {
+        let changes = [1, -2, 3, 1];
+        let mut device = FrequencyDevice::new();
+        assert_eq!(device.apply(1), None);
+        assert_eq!(device.apply(-2), None);
+        assert_eq!(
+            (device.frequency(), device.min(), device.max()),
+            (-1, -1, 1)
+        );
+        assert_eq!(device.first_repeat(), None);
+
+        let mut device = FrequencyDevice::new();
+        device.apply_pass(&changes);
+        assert_eq!(device.frequency(), part_a(&changes));
+        assert_eq!((device.passes(), device.iterations()), (1, 4));
+        assert_eq!(device.first_repeat(), None);
+        assert_eq!(device.distinct(), 5);
+
+        device.apply_pass(&changes);
+        let first = Revisit {
+            iteration: 6,
+            pass: 1,
+        };
+        assert_eq!(device.first_repeat(), Some((2, first)));
+        assert_eq!(device.revisit(2), Some(first));
+        assert_eq!(
+            device.revisited().keys().cloned().collect::<Vec<_>>(),
+            vec![2]
+        );
+        assert_eq!((device.min(), device.max()), (-1, 6));
+
+        device.apply_pass(&changes);
+        assert_eq!(
+            device.revisit(5),
+            Some(Revisit {
+                iteration: 10,
+                pass: 2
+            })
+        );
+        assert_eq!(device.revisit(7), None);
+        assert_eq!(device.first_repeat().map(|(freq, _)| freq), Some(2));
+    }
+
+    #[test]
+    fn device_matches_part_b() {
+        for changes in &[vec![1, -1], vec![3, 3, 4, -2, -4], vec![-6, 3, 8, 5, -6]] {
+            let mut device = FrequencyDevice::new();
+            while device.first_repeat().is_none() {
+                device.apply_pass(changes);
+            }
+            assert_eq!(
+                device.first_repeat().map(|(freq, _)| freq),
+                part_b(changes).ok()
+            );
+        }
+    }
+
+    #[test]
+    fn example() {
+        assert_eq!(solve("+1\n-2\n+3\n+1\n").unwrap(), Output::new(3, 2));
+    }
 
     #[test]
     fn full() {
-        assert_eq!(run(), Output { a: 497, b: 558 });
+        assert_eq!(run().unwrap(), Output::new(497, 558));
     }
 }
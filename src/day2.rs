@@ -1,74 +1,238 @@
-use super::{parse_input, Output};
+use super::{load_input, AocError, Output, Result, Solution};
 use itertools::Itertools;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 
 const INPUT: &str = "day2.txt";
-const NUM_LOWER_ALPHA: usize = 26;
 
-pub fn run() -> Output<usize, String> {
-    let input: Vec<String> = parse_input(INPUT).lines().map(Into::into).collect();
-    Output {
-        a: part_a(&input),
-        b: part_b(&input),
+pub struct Day2;
+
+impl Solution for Day2 {
+    type Input = Vec<String>;
+    type A = usize;
+    type B = String;
+
+    fn parse(&self, raw: &str) -> Result<Self::Input> {
+        Ok(raw.lines().map(Into::into).collect())
+    }
+
+    fn part_a(&self, input: &Self::Input) -> Result<Self::A> {
+        Ok(part_a(input))
+    }
+
+    fn part_b(&self, input: &Self::Input) -> Result<Self::B> {
+        part_b(input).map(|found| found.common)
+    }
+}
+
+/// Solve both parts for the puzzle input `input`
+pub fn solve(input: &str) -> Result<Output<usize, String>> {
+    Day2.solve(input)
+}
+
+pub fn run() -> Result<Output<usize, String>> {
+    solve(&load_input(INPUT)?)
+}
+
+/// Which character repeats are counted by the box-ID checksum
+#[derive(Clone, Debug, PartialEq)]
+pub struct Checksum {
+    /// Count upper and lower case of the same letter separately
+    pub case_sensitive: bool,
+    /// Number of occurrences of a character to tally IDs by
+    pub repeats: Vec<usize>,
+}
+
+impl Default for Checksum {
+    /// The puzzle's checksum, IDs with some letter exactly twice or three times
+    fn default() -> Self {
+        Self {
+            case_sensitive: false,
+            repeats: vec![2, 3],
+        }
+    }
+}
+
+impl Checksum {
+    /// For each repeat count `n` the number of IDs containing some character exactly `n` times
+    pub fn tally<S: AsRef<str>>(&self, ids: &[S]) -> BTreeMap<usize, usize> {
+        let mut tally: BTreeMap<usize, usize> = self.repeats.iter().map(|&n| (n, 0)).collect();
+        for id in ids {
+            let counts = self.occurrences(id.as_ref());
+            for (n, ids) in tally.iter_mut() {
+                if counts.contains(n) {
+                    *ids += 1;
+                }
+            }
+        }
+        tally
+    }
+
+    /// Product of the tallies
+    pub fn checksum<S: AsRef<str>>(&self, ids: &[S]) -> usize {
+        self.tally(ids).values().product()
+    }
+
+    /// Number of occurrences of each distinct character in `id`
+    fn occurrences(&self, id: &str) -> Vec<usize> {
+        if id.is_ascii() {
+            let mut lookup = [0_usize; 128];
+            for byte in id.bytes() {
+                let byte = if self.case_sensitive {
+                    byte
+                } else {
+                    byte.to_ascii_lowercase()
+                };
+                lookup[byte as usize] += 1;
+            }
+            lookup.iter().cloned().filter(|&n| n > 0).collect()
+        } else {
+            let mut lookup = HashMap::new();
+            for ch in id.chars() {
+                if self.case_sensitive {
+                    *lookup.entry(ch).or_insert(0) += 1;
+                } else {
+                    for ch in ch.to_lowercase() {
+                        *lookup.entry(ch).or_insert(0) += 1;
+                    }
+                }
+            }
+            lookup.into_values().collect()
+        }
     }
 }
 
 pub fn part_a(input: &[String]) -> usize {
-    let (two, three) = input.iter().fold((0, 0), |(two, three), word| {
-        let mut lookup = [0_usize; NUM_LOWER_ALPHA];
-        for ch in word
-            .chars()
-            .filter(|ch| ch.is_ascii_alphabetic())
-            .map(|ch| ch.to_ascii_lowercase())
+    Checksum::default().checksum(input)
+}
+
+/// Two IDs which differ in exactly one position
+#[derive(Clone, Debug, PartialEq)]
+pub struct Match {
+    pub ids: (String, String),
+    /// Indices of the IDs in the input
+    pub indices: (usize, usize),
+    /// Character position at which the IDs differ
+    pub position: usize,
+    /// The IDs without the differing character
+    pub common: String,
+}
+
+/// The first pair of IDs which differ in exactly one position, the pair whose later ID comes
+/// first and then whose earlier ID comes first
+///
+/// All IDs must have the same number of characters, the first ID of a different length is
+/// reported as an error with its line.
+pub fn part_b(input: &[String]) -> Result<Match> {
+    if let Some(first) = input.first() {
+        let len = first.chars().count();
+        if let Some((idx, id)) = input
+            .iter()
+            .enumerate()
+            .find(|(_, id)| id.chars().count() != len)
         {
-            let idx = (ch as usize) - 'a' as usize;
-            // could overflow
-            lookup[idx] += 1;
+            return Err(AocError::parse(
+                idx + 1,
+                id,
+                format!("expected {} characters like the first ID", len),
+            ));
         }
-        let two_inc = if lookup.iter().any(|&v| v == 2) { 1 } else { 0 };
-        let three_inc = if lookup.iter().any(|&v| v == 3) { 1 } else { 0 };
-        (two + two_inc, three + three_inc)
-    });
+    }
 
-    two * three
+    let (i, j) = first_pair(input)
+        .ok_or_else(|| AocError::unsolvable("no two IDs differ by exactly one character"))?;
+    let (a, b) = (&input[i], &input[j]);
+    let position = a
+        .chars()
+        .zip(b.chars())
+        .position(|(a, b)| a != b)
+        .expect("the IDs differ in one position; qed");
+
+    Ok(Match {
+        ids: (a.clone(), b.clone()),
+        indices: (i, j),
+        position,
+        common: a
+            .chars()
+            .enumerate()
+            .filter(|&(pos, _)| pos != position)
+            .map(|(_, ch)| ch)
+            .collect(),
+    })
 }
 
-pub fn part_b(input: &[String]) -> String {
-    for (str1, str2) in input.iter().tuple_combinations() {
-        // Get number of chars that differ, O(n)
-        //
-        // NOTE: we could collect this into a `String` directly here but would allocate
-        // a String for every iteration with is slow                             )
-        let diff =
-            str1.chars().zip(str2.chars()).fold(
-                0,
-                |diff, (str1, str2)| {
-                    if str1 == str2 {
-                        diff
-                    } else {
-                        diff + 1
-                    }
-                },
-            );
-
-        // Construct and return a new string, O(n) + allocation
-        //
-        // NOTE: we could also return the `idx` to the character to remove in the String from
-        // `fold` above but `String::remove` is O(n) + allocation for `cloning the old String`
-        if diff == 1 {
-            return str1
-                .chars()
-                .zip(str2.chars())
-                .filter(|(a, b)| a == b)
-                .map(|(a, _b)| a)
-                .collect();
+/// The indices `(i, j)` with `i < j` of the first IDs which differ in exactly one position,
+/// with the smallest `j` and then the smallest `i`
+///
+/// Every ID is keyed once per position by the text around that position, so a later ID with
+/// a key seen before differs from the earlier ID in at most that position.
+fn first_pair(ids: &[String]) -> Option<(usize, usize)> {
+    let mut seen: HashMap<(usize, &str, &str), usize> = HashMap::new();
+    for (j, id) in ids.iter().enumerate() {
+        let mut found = None;
+        for (pos, (at, ch)) in id.char_indices().enumerate() {
+            let key = (pos, &id[..at], &id[at + ch.len_utf8()..]);
+            match seen.get(&key) {
+                Some(&i) if ids[i] != *id => found = Some(found.map_or(i, |f: usize| f.min(i))),
+                Some(_) => (),
+                None => {
+                    seen.insert(key, j);
+                }
+            }
+        }
+        if let Some(i) = found {
+            return Some((i, j));
         }
     }
-    unreachable!("the input should have two items that differ just one; qed");
+    None
+}
+
+/// All pairs of indices `(i, j)` with `i < j` of IDs with the same number of characters which
+/// differ in at most `k` positions, in ascending order
+///
+/// Two such IDs are equal once the positions where they differ are masked, so for every choice
+/// of `k` positions the IDs are grouped by the remaining characters. This takes
+/// O(n · C(len, k) · len) instead of comparing all pairs.
+pub fn pairs_within<S: AsRef<str>>(ids: &[S], k: usize) -> Vec<(usize, usize)> {
+    let ids: Vec<Vec<char>> = ids.iter().map(|id| id.as_ref().chars().collect()).collect();
+    let mut by_len: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+    for (idx, id) in ids.iter().enumerate() {
+        by_len.entry(id.len()).or_default().push(idx);
+    }
+
+    let mut pairs = BTreeSet::new();
+    for (&len, members) in by_len.iter().filter(|(_, members)| members.len() > 1) {
+        let masks: Vec<Vec<usize>> = match k.min(len) {
+            0 => vec![Vec::new()],
+            k => (0..len).combinations(k).collect(),
+        };
+
+        for mask in masks {
+            let mut masked = vec![false; len];
+            for &pos in &mask {
+                masked[pos] = true;
+            }
+            let mut groups: HashMap<Vec<char>, Vec<usize>> = HashMap::new();
+            for &idx in members {
+                let key = ids[idx]
+                    .iter()
+                    .zip(&masked)
+                    .filter(|&(_, &masked)| !masked)
+                    .map(|(&ch, _)| ch)
+                    .collect();
+                groups.entry(key).or_default().push(idx);
+            }
+            for group in groups.values() {
+                pairs.extend(group.iter().tuple_combinations().map(|(&i, &j)| (i, j)));
+            }
+        }
+    }
+    pairs.into_iter().collect()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::Rng;
 
     #[test]
     fn test_part_a() {
@@ -84,6 +248,32 @@ mod tests {
         assert_eq!(part_a(&input), 12);
     }
 
+    #[test]
+    fn checksum_alphabet() {
+        let ids = ["aAb", "a1b1", "ééa", "ÉéÈ"];
+        let insensitive = Checksum::default();
+        assert_eq!(
+            insensitive.tally(&ids),
+            vec![(2, 4), (3, 0)].into_iter().collect()
+        );
+
+        let sensitive = Checksum {
+            case_sensitive: true,
+            repeats: vec![1, 2, 3],
+        };
+        assert_eq!(
+            sensitive.tally(&ids),
+            vec![(1, 4), (2, 2), (3, 0)].into_iter().collect()
+        );
+        assert_eq!(sensitive.checksum(&ids), 0);
+
+        let digits = Checksum {
+            case_sensitive: true,
+            repeats: vec![4],
+        };
+        assert_eq!(digits.checksum(&["1111", "11a11", "ÿÿÿÿ"]), 3);
+    }
+
     #[test]
     fn test_part_b() {
         let input = vec![
@@ -95,17 +285,96 @@ mod tests {
             "axcye".to_string(),
             "wvxyz".to_string(),
         ];
-        assert_eq!(part_b(&input), "fgij".to_string());
+        assert_eq!(
+            part_b(&input).unwrap(),
+            Match {
+                ids: ("fghij".to_string(), "fguij".to_string()),
+                indices: (1, 4),
+                position: 2,
+                common: "fgij".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn part_b_errors() {
+        let none = vec!["abc".to_string(), "xyz".to_string(), "abc".to_string()];
+        assert_eq!(first_pair(&none), None);
+        let repeated = vec!["abc".to_string(), "abc".to_string(), "abé".to_string()];
+        assert_eq!(first_pair(&repeated), Some((0, 2)));
+        assert!(matches!(part_b(&none), Err(AocError::Unsolvable(_))));
+
+        let ragged = vec!["abc".to_string(), "abd".to_string(), "ab".to_string()];
+        assert_eq!(
+            part_b(&ragged).unwrap_err().to_string(),
+            "line 3: expected 3 characters like the first ID `ab`"
+        );
+    }
+
+    #[test]
+    fn pairs() {
+        let ids = ["abcd", "abce", "abed", "xbcd", "abcd", "abc", "ab€"];
+        assert_eq!(pairs_within(&ids, 0), vec![(0, 4)]);
+        assert_eq!(
+            pairs_within(&ids, 1),
+            vec![
+                (0, 1),
+                (0, 2),
+                (0, 3),
+                (0, 4),
+                (1, 4),
+                (2, 4),
+                (3, 4),
+                (5, 6)
+            ]
+        );
+        assert_eq!(pairs_within(&ids, 2).len(), 11);
+        assert_eq!(pairs_within(&ids, 10).len(), 11);
+        assert_eq!(pairs_within(&["", ""], 1), vec![(0, 1)]);
+    }
+
+    #[test]
+    fn pairs_match_brute_force() {
+        let mut rng = Rng::new(2);
+        let ids: Vec<String> = (0..60)
+            .map(|_| {
+                let len = 3 + rng.below(2);
+                (0..len)
+                    .map(|_| (b'a' + rng.below(3) as u8) as char)
+                    .collect()
+            })
+            .collect();
+
+        for k in 0..4 {
+            let expected: Vec<(usize, usize)> = (0..ids.len())
+                .tuple_combinations()
+                .filter(|&(i, j)| {
+                    ids[i].len() == ids[j].len()
+                        && ids[i]
+                            .chars()
+                            .zip(ids[j].chars())
+                            .filter(|(a, b)| a != b)
+                            .count()
+                            <= k
+                })
+                .collect();
+            assert_eq!(pairs_within(&ids, k), expected, "k = {}", k);
+        }
+
+        for end in 0..=ids.len() {
+            let differing = pairs_within(&ids[..end], 1)
+                .into_iter()
+                .filter(|&(i, j)| ids[i] != ids[j])
+                .min_by_key(|&(i, j)| (j, i));
+            assert_eq!(first_pair(&ids[..end]), differing, "{:?}", &ids[..end]);
+        }
     }
 
     #[test]
     fn full() {
         assert_eq!(
-            run(),
-            Output {
-                a: 6474,
-                b: String::from("mxhwoglxgeauywfkztndcvjqr")
-            }
+            run().unwrap(),
+            Output::new(6474, String::from("mxhwoglxgeauywfkztndcvjqr"))
         );
     }
 }
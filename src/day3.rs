@@ -1,14 +1,20 @@
-use super::{parse_input, Output};
+use super::{load_input, parse_lines, AocError, Output, Result, Solution};
 use regex::Regex;
+use std::collections::HashMap;
+use std::fmt::{self, Display};
+use std::str::FromStr;
 
 lazy_static! {
     static ref RE: Regex = Regex::new(r"#(\d+) @ (\d+),(\d+): (\d+)x(\d+)").unwrap();
 }
 
 const INPUT: &str = "day3.txt";
+/// Bounding boxes with more square inches than this are counted per rectangle between claim
+/// edges, and claims with more rectangles than this aren't counted at all
+const DENSE_LIMIT: usize = 1 << 24;
 
-#[derive(Debug)]
-struct Square {
+#[derive(Clone, Debug, PartialEq)]
+pub struct Square {
     pub id: usize,
     pub from_left: usize,
     pub from_top: usize,
@@ -16,71 +22,660 @@ struct Square {
     pub depth: usize,
 }
 
-impl From<&str> for Square {
-    fn from(input: &str) -> Self {
-        let caps = RE.captures(input).unwrap();
-        Self {
-            id: caps[1].parse().expect("well-formed input; qed"),
-            from_left: caps[2].parse().expect("well-formed input; qed"),
-            from_top: caps[3].parse().expect("well-formed input; qed"),
-            width: caps[4].parse().expect("well-formed input; qed"),
-            depth: caps[5].parse().expect("well-formed input; qed"),
+impl FromStr for Square {
+    type Err = &'static str;
+
+    fn from_str(input: &str) -> std::result::Result<Self, Self::Err> {
+        let caps = RE
+            .captures(input)
+            .ok_or("expected claim `#id @ left,top: widthxdepth`")?;
+        let num = |idx: usize| caps[idx].parse().map_err(|_| "number out of range");
+        Ok(Self {
+            id: num(1)?,
+            from_left: num(2)?,
+            from_top: num(3)?,
+            width: num(4)?,
+            depth: num(5)?,
+        })
+    }
+}
+
+impl Display for Square {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "#{} @ {},{}: {}x{}",
+            self.id, self.from_left, self.from_top, self.width, self.depth
+        )
+    }
+}
+
+impl Square {
+    /// The square inches covered by the claim, `None` if they don't fit in a `usize`
+    pub fn bounds(&self) -> Option<Bounds> {
+        Some(Bounds {
+            left: self.from_left,
+            top: self.from_top,
+            right: self.from_left.checked_add(self.width)?,
+            bottom: self.from_top.checked_add(self.depth)?,
+        })
+    }
+}
+
+/// Bounds of the `idx`th claim, an error if they are out of range
+fn claimed(idx: usize, square: &Square) -> Result<Bounds> {
+    square
+        .bounds()
+        .ok_or_else(|| AocError::parse(idx + 1, &square.to_string(), "claim is out of range"))
+}
+
+/// A rectangle of square inches, `right` and `bottom` are exclusive
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct Bounds {
+    pub left: usize,
+    pub top: usize,
+    pub right: usize,
+    pub bottom: usize,
+}
+
+impl Bounds {
+    pub fn width(&self) -> usize {
+        self.right - self.left
+    }
+
+    pub fn depth(&self) -> usize {
+        self.bottom - self.top
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.width() == 0 || self.depth() == 0
+    }
+
+    pub fn contains(&self, x: usize, y: usize) -> bool {
+        (self.left..self.right).contains(&x) && (self.top..self.bottom).contains(&y)
+    }
+
+    /// The square inches in both, `None` if there are none
+    pub fn intersection(&self, other: &Bounds) -> Option<Bounds> {
+        let overlap = Bounds {
+            left: self.left.max(other.left),
+            top: self.top.max(other.top),
+            right: self.right.min(other.right),
+            bottom: self.bottom.min(other.bottom),
+        };
+        if overlap.left < overlap.right && overlap.top < overlap.bottom {
+            Some(overlap)
+        } else {
+            None
+        }
+    }
+
+    /// Smallest bounds containing both, an empty rectangle doesn't count
+    fn union(self, other: Bounds) -> Bounds {
+        if self.is_empty() {
+            return other;
+        } else if other.is_empty() {
+            return self;
         }
+        Bounds {
+            left: self.left.min(other.left),
+            top: self.top.min(other.top),
+            right: self.right.max(other.right),
+            bottom: self.bottom.max(other.bottom),
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Counts {
+    /// Row by row over the bounds of the fabric
+    Dense(Vec<u32>),
+    /// Row by row over the rectangles between consecutive distinct claim edges
+    Compressed {
+        xs: Vec<usize>,
+        ys: Vec<usize>,
+        grid: Vec<u32>,
+    },
+    /// Nothing per square inch when even the rectangles are too many, only the overlap swept
+    /// once while the other queries go through every claim
+    Sparse { overlap: usize },
+}
+
+/// The index of the span between consecutive `edges` which holds `at`
+fn span(edges: &[usize], at: usize) -> Option<usize> {
+    match edges.binary_search(&at) {
+        Ok(idx) if idx + 1 < edges.len() => Some(idx),
+        Ok(_) => None,
+        Err(idx) if idx == 0 || idx == edges.len() => None,
+        Err(idx) => Some(idx - 1),
     }
 }
 
-pub fn run() -> Output<usize, usize> {
-    let squares: Vec<Square> = parse_input(INPUT).lines().map(Into::into).collect();
-    let mut grid = [[0_u8; 1000]; 1000];
+/// The distinct values of `edges` in order
+fn distinct(mut edges: Vec<usize>) -> Vec<usize> {
+    edges.sort_unstable();
+    edges.dedup();
+    edges
+}
+
+/// The claims together with the number of claims covering every square inch
+///
+/// The counts cover only the bounding box of the claims. When the bounding box is too large
+/// they are kept per rectangle between the edges of the claims instead of per square inch,
+/// and when there are too many of those they are not kept at all.
+#[derive(Debug)]
+pub struct Fabric {
+    squares: Vec<Square>,
+    bounds: Bounds,
+    counts: Counts,
+}
+
+impl Fabric {
+    /// Lay out `squares` on a fabric just large enough to hold all of them
+    pub fn new(squares: Vec<Square>) -> Result<Self> {
+        let mut bounds = Bounds::default();
+        for (idx, square) in squares.iter().enumerate() {
+            bounds = bounds.union(claimed(idx, square)?);
+        }
+        Ok(Self::layout(squares, bounds))
+    }
 
-    for square in squares.iter() {
-        for row in grid.iter_mut().skip(square.from_top).take(square.depth) {
-            for seen in row.iter_mut().skip(square.from_left).take(square.width) {
-                *seen += 1;
+    /// Lay out `squares` on a fabric of `width` by `depth` square inches, claims which extend
+    /// beyond its edges are an error
+    pub fn with_size(squares: Vec<Square>, width: usize, depth: usize) -> Result<Self> {
+        for (idx, square) in squares.iter().enumerate() {
+            match square.bounds() {
+                Some(claimed) if claimed.right <= width && claimed.bottom <= depth => (),
+                _ => {
+                    return Err(AocError::parse(
+                        idx + 1,
+                        &square.to_string(),
+                        format!("claim extends beyond the {}x{} fabric", width, depth),
+                    ))
+                }
             }
         }
+        let bounds = Bounds {
+            left: 0,
+            top: 0,
+            right: width,
+            bottom: depth,
+        };
+        Ok(Self::layout(squares, bounds))
     }
 
-    Output {
-        a: part_a(&mut grid[..]),
-        b: part_b(&squares, &mut grid[..]),
+    fn layout(squares: Vec<Square>, bounds: Bounds) -> Self {
+        let claims: Vec<Bounds> = squares
+            .iter()
+            .map(|square| square.bounds().expect("claims are checked to fit; qed"))
+            .filter(|claimed| !claimed.is_empty())
+            .collect();
+        let dense = bounds
+            .width()
+            .checked_mul(bounds.depth())
+            .filter(|&area| area <= DENSE_LIMIT);
+        let counts = match dense {
+            Some(area) => {
+                let mut grid = vec![0; area];
+                for claimed in &claims {
+                    for y in claimed.top..claimed.bottom {
+                        let start = (y - bounds.top) * bounds.width() + claimed.left - bounds.left;
+                        for seen in &mut grid[start..start + claimed.width()] {
+                            *seen += 1;
+                        }
+                    }
+                }
+                Counts::Dense(grid)
+            }
+            None => Self::compress(&claims).unwrap_or_else(|| Counts::Sparse {
+                overlap: sweep(&squares).expect("claims are checked to fit; qed"),
+            }),
+        };
+
+        Self {
+            squares,
+            bounds,
+            counts,
+        }
+    }
+
+    /// Count the claims per rectangle between their edges, marking where every claim starts
+    /// and ends along each row of rectangles and summing the marks, `None` if there are more
+    /// rectangles than `DENSE_LIMIT`
+    fn compress(claims: &[Bounds]) -> Option<Counts> {
+        let xs = distinct(claims.iter().flat_map(|c| vec![c.left, c.right]).collect());
+        let ys = distinct(claims.iter().flat_map(|c| vec![c.top, c.bottom]).collect());
+        let width = xs.len().saturating_sub(1);
+        let depth = ys.len().saturating_sub(1);
+        if width.saturating_mul(depth) > DENSE_LIMIT {
+            return None;
+        }
+
+        let index = |edges: &[usize], at| {
+            edges
+                .binary_search(&at)
+                .expect("every claim edge is an edge; qed")
+        };
+        let mut marks = vec![0i64; (width + 1) * depth];
+        for claimed in claims {
+            let (left, right) = (index(&xs, claimed.left), index(&xs, claimed.right));
+            for row in index(&ys, claimed.top)..index(&ys, claimed.bottom) {
+                marks[row * (width + 1) + left] += 1;
+                marks[row * (width + 1) + right] -= 1;
+            }
+        }
+
+        let mut grid = Vec::with_capacity(width * depth);
+        for row in marks.chunks(width + 1) {
+            let mut seen = 0;
+            for mark in &row[..width] {
+                seen += mark;
+                grid.push(seen as u32);
+            }
+        }
+        Some(Counts::Compressed { xs, ys, grid })
+    }
+
+    pub fn squares(&self) -> &[Square] {
+        &self.squares
+    }
+
+    /// The square inches covered by the counts
+    pub fn bounds(&self) -> Bounds {
+        self.bounds
+    }
+
+    /// Number of claims covering the square inch at `x`, `y`
+    pub fn claims(&self, x: usize, y: usize) -> u32 {
+        match self.counts {
+            _ if !self.bounds.contains(x, y) => 0,
+            Counts::Dense(ref grid) => {
+                grid[(y - self.bounds.top) * self.bounds.width() + x - self.bounds.left]
+            }
+            Counts::Compressed {
+                ref xs,
+                ref ys,
+                ref grid,
+            } => match (span(xs, x), span(ys, y)) {
+                (Some(col), Some(row)) => grid[row * (xs.len() - 1) + col],
+                _ => 0,
+            },
+            Counts::Sparse { .. } => self
+                .squares
+                .iter()
+                .filter(|square| square.bounds().is_some_and(|claim| claim.contains(x, y)))
+                .count() as u32,
+        }
+    }
+
+    /// Number of square inches covered by two or more claims
+    pub fn overlap(&self) -> usize {
+        match self.counts {
+            Counts::Dense(ref grid) => grid.iter().filter(|&&n| n > 1).count(),
+            Counts::Compressed {
+                ref xs,
+                ref ys,
+                ref grid,
+            } => (0..grid.len())
+                .filter(|&idx| grid[idx] > 1)
+                .map(|idx| {
+                    let (row, col) = (idx / (xs.len() - 1), idx % (xs.len() - 1));
+                    (xs[col + 1] - xs[col]) * (ys[row + 1] - ys[row])
+                })
+                .sum(),
+            Counts::Sparse { overlap } => overlap,
+        }
+    }
+
+    /// Whether no other claim covers any square inch of `square`
+    pub fn is_intact(&self, square: &Square) -> bool {
+        let claimed = match square.bounds() {
+            Some(claimed) if claimed.is_empty() => return true,
+            Some(claimed) if claimed.intersection(&self.bounds) == Some(claimed) => claimed,
+            _ => return false,
+        };
+        match self.counts {
+            Counts::Dense(_) => (claimed.top..claimed.bottom)
+                .all(|y| (claimed.left..claimed.right).all(|x| self.claims(x, y) == 1)),
+            Counts::Compressed {
+                ref xs,
+                ref ys,
+                ref grid,
+            } => {
+                let spans = |edges: &[usize], from, to| match (span(edges, from), span(edges, to)) {
+                    (Some(first), Some(last)) => Some(first..=last),
+                    _ => None,
+                };
+                match (
+                    spans(xs, claimed.left, claimed.right - 1),
+                    spans(ys, claimed.top, claimed.bottom - 1),
+                ) {
+                    (Some(cols), Some(mut rows)) => rows.all(|row| {
+                        grid[row * (xs.len() - 1)..][cols.clone()]
+                            .iter()
+                            .all(|&n| n == 1)
+                    }),
+                    _ => false,
+                }
+            }
+            Counts::Sparse { .. } => {
+                // covered once everywhere if the claims within it don't overlap and leave no
+                // gaps
+                let parts: Vec<Bounds> = self
+                    .squares
+                    .iter()
+                    .filter_map(|square| square.bounds()?.intersection(&claimed))
+                    .collect();
+                let disjoint = parts.iter().enumerate().all(|(idx, part)| {
+                    parts[idx + 1..]
+                        .iter()
+                        .all(|other| part.intersection(other).is_none())
+                });
+                disjoint
+                    && parts
+                        .iter()
+                        .map(|part| part.width() * part.depth())
+                        .sum::<usize>()
+                        == claimed.width() * claimed.depth()
+            }
+        }
     }
 }
 
-fn part_a(grid: &mut [[u8; 1000]]) -> usize {
-    grid.iter().fold(0, |acc, row| {
-        let sum = row.iter().filter(|&&s| s > 1).count();
-        acc + sum
-    })
+pub struct Day3;
+
+impl Solution for Day3 {
+    type Input = Fabric;
+    type A = usize;
+    type B = usize;
+
+    fn parse(&self, raw: &str) -> Result<Self::Input> {
+        Fabric::new(parse_lines(raw)?)
+    }
+
+    fn part_a(&self, input: &Self::Input) -> Result<Self::A> {
+        Ok(part_a(input))
+    }
+
+    fn part_b(&self, input: &Self::Input) -> Result<Self::B> {
+        part_b(input)
+    }
 }
 
-fn part_b(squares: &[Square], grid: &mut [[u8; 1000]]) -> usize {
-    for square in squares.iter() {
-        let claimed_once: bool = grid
+/// Solve both parts for the puzzle input `input`
+pub fn solve(input: &str) -> Result<Output<usize, usize>> {
+    Day3.solve(input)
+}
+
+pub fn run() -> Result<Output<usize, usize>> {
+    solve(&load_input(INPUT)?)
+}
+
+/// How to count the square inches claimed more than once
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Method {
+    /// Count the claims on every square inch of a `Fabric`, O(total area)
+    Grid,
+    /// Sweep over the claim edges, O(n²) in the number of claims whatever their size
+    Sweep,
+}
+
+/// Number of square inches covered by two or more of `squares`
+pub fn overlap_area(squares: &[Square], method: Method) -> Result<usize> {
+    match method {
+        Method::Grid => Fabric::new(squares.to_vec()).map(|fabric| fabric.overlap()),
+        Method::Sweep => sweep(squares),
+    }
+}
+
+/// Sweep a vertical line from left to right over the edges of the claims
+///
+/// The line keeps the number of claims covering each interval between the distinct top and
+/// bottom edges, between two consecutive left or right edges the covered length stays the same.
+fn sweep(squares: &[Square]) -> Result<usize> {
+    let mut edges = Vec::new();
+    let mut ys = Vec::new();
+    for (idx, square) in squares.iter().enumerate() {
+        let claimed = claimed(idx, square)?;
+        if claimed.is_empty() {
+            continue;
+        }
+        edges.push((claimed.left, 1, claimed.top, claimed.bottom));
+        edges.push((claimed.right, -1, claimed.top, claimed.bottom));
+        ys.push(claimed.top);
+        ys.push(claimed.bottom);
+    }
+    edges.sort_unstable_by_key(|&(x, ..)| x);
+    ys.sort_unstable();
+    ys.dedup();
+
+    let mut counts = vec![0_i32; ys.len().saturating_sub(1)];
+    let mut area = 0;
+    let mut last_x = 0;
+    for (x, delta, top, bottom) in edges {
+        if x > last_x {
+            let covered: usize = counts
+                .iter()
+                .zip(ys.windows(2))
+                .filter(|&(&count, _)| count > 1)
+                .map(|(_, interval)| interval[1] - interval[0])
+                .sum();
+            area += covered * (x - last_x);
+            last_x = x;
+        }
+        let from = ys
+            .binary_search(&top)
+            .expect("every top edge is in `ys`; qed");
+        let to = ys
+            .binary_search(&bottom)
+            .expect("every bottom edge is in `ys`; qed");
+        for count in &mut counts[from..to] {
+            *count += delta;
+        }
+    }
+    Ok(area)
+}
+
+/// Where a claim overlaps another claim
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Intersection {
+    /// Index of the other claim
+    pub index: usize,
+    /// ID of the other claim
+    pub id: usize,
+    pub bounds: Bounds,
+}
+
+/// Which claims overlap which other claims
+///
+/// Claims are indexed by the square buckets of the fabric they touch, with buckets about as
+/// large as the average claim, so only claims sharing a bucket are compared.
+#[derive(Debug)]
+pub struct Overlaps<'a> {
+    squares: &'a [Square],
+    bucket: usize,
+    buckets: HashMap<(usize, usize), Vec<usize>>,
+    graph: Vec<Vec<Intersection>>,
+}
+
+impl<'a> Overlaps<'a> {
+    pub fn new(squares: &'a [Square]) -> Result<Self> {
+        let claims = squares
             .iter()
-            .skip(square.from_top)
-            .take(square.depth)
-            .map(|r| {
-                r.iter()
-                    .skip(square.from_left)
-                    .take(square.width)
-                    .all(|&c| c == 1)
-            })
-            .all(|c| c);
-        if claimed_once {
-            return square.id;
+            .enumerate()
+            .map(|(idx, square)| claimed(idx, square))
+            .collect::<Result<Vec<_>>>()?;
+        let sides: usize = claims
+            .iter()
+            .map(|claim| claim.width().max(claim.depth()))
+            .sum();
+        let bucket = (sides / claims.len().max(1)).max(1);
+
+        let mut overlaps = Self {
+            squares,
+            bucket,
+            buckets: HashMap::new(),
+            graph: vec![Vec::new(); squares.len()],
+        };
+        for (idx, claim) in claims.iter().enumerate() {
+            let mut candidates = Vec::new();
+            for key in overlaps.keys(claim) {
+                let bucket = overlaps.buckets.entry(key).or_default();
+                candidates.extend_from_slice(bucket);
+                bucket.push(idx);
+            }
+            candidates.sort_unstable();
+            candidates.dedup();
+
+            for other in candidates {
+                if let Some(bounds) = claim.intersection(&claims[other]) {
+                    overlaps.graph[idx].push(Intersection {
+                        index: other,
+                        id: squares[other].id,
+                        bounds,
+                    });
+                    overlaps.graph[other].push(Intersection {
+                        index: idx,
+                        id: squares[idx].id,
+                        bounds,
+                    });
+                }
+            }
         }
+        Ok(overlaps)
     }
-    unreachable!("Exactly one ID claimed; qed")
+
+    /// Keys of the buckets touched by `claim`
+    fn keys(&self, claim: &Bounds) -> impl Iterator<Item = (usize, usize)> {
+        let size = self.bucket;
+        let (columns, rows) = if claim.is_empty() {
+            (0..0, 0..0)
+        } else {
+            (
+                claim.left / size..(claim.right - 1) / size + 1,
+                claim.top / size..(claim.bottom - 1) / size + 1,
+            )
+        };
+        rows.flat_map(move |y| columns.clone().map(move |x| (x, y)))
+    }
+
+    /// The claims overlapping the claim at `idx` in the order they appear
+    pub fn of(&self, idx: usize) -> &[Intersection] {
+        &self.graph[idx]
+    }
+
+    /// Every claim together with the claims overlapping it
+    pub fn iter(&self) -> impl Iterator<Item = (&Square, &[Intersection])> {
+        self.squares
+            .iter()
+            .zip(self.graph.iter().map(Vec::as_slice))
+    }
+
+    /// IDs of the claims covering the square inch at `x`, `y`
+    pub fn covering(&self, x: usize, y: usize) -> Vec<usize> {
+        let key = (x / self.bucket, y / self.bucket);
+        self.buckets
+            .get(&key)
+            .into_iter()
+            .flatten()
+            .map(|&idx| &self.squares[idx])
+            .filter(|square| square.bounds().is_some_and(|claim| claim.contains(x, y)))
+            .map(|square| square.id)
+            .collect()
+    }
+
+    /// IDs of the claims which don't overlap any other claim
+    pub fn intact(&self) -> Vec<usize> {
+        self.iter()
+            .filter(|(_, overlapping)| overlapping.is_empty())
+            .map(|(square, _)| square.id)
+            .collect()
+    }
+}
+
+/// Netpbm image formats to render a `Fabric` as
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Image {
+    /// Greyscale, from black for unclaimed to white for intact claims
+    Pgm,
+    /// Colour, uncontested claims are blue, contested red and intact claims green
+    Ppm,
+}
+
+impl Image {
+    fn magic(self) -> &'static str {
+        match self {
+            Image::Pgm => "P5",
+            Image::Ppm => "P6",
+        }
+    }
+
+    /// Pixel of a square inch claimed `claims` times, `intact` if it belongs to an intact claim
+    fn pixel(self, claims: u32, intact: bool) -> &'static [u8] {
+        match (self, claims, intact) {
+            (Image::Pgm, 0, _) => &[0],
+            (Image::Pgm, _, true) => &[255],
+            (Image::Pgm, 1, _) => &[85],
+            (Image::Pgm, _, _) => &[170],
+            (Image::Ppm, 0, _) => &[0, 0, 0],
+            (Image::Ppm, _, true) => &[0, 200, 0],
+            (Image::Ppm, 1, _) => &[40, 60, 160],
+            (Image::Ppm, _, _) => &[220, 30, 30],
+        }
+    }
+}
+
+/// Render the bounds of `fabric` as a binary Netpbm image with one pixel per square inch,
+/// `None` if the fabric is too large to render
+pub fn render(fabric: &Fabric, image: Image) -> Option<Vec<u8>> {
+    let bounds = fabric.bounds();
+    let (width, depth) = (bounds.width(), bounds.depth());
+    if width.checked_mul(depth)? > DENSE_LIMIT {
+        return None;
+    }
+
+    let mut intact = vec![false; width * depth];
+    for square in fabric.squares().iter().filter(|s| fabric.is_intact(s)) {
+        let claim = square.bounds().expect("claims are checked to fit; qed");
+        for y in claim.top..claim.bottom {
+            let row = (y - bounds.top) * width;
+            for x in claim.left..claim.right {
+                intact[row + x - bounds.left] = true;
+            }
+        }
+    }
+
+    let mut bytes = format!("{}\n{} {}\n255\n", image.magic(), width, depth).into_bytes();
+    for y in 0..depth {
+        for x in 0..width {
+            let claims = fabric.claims(bounds.left + x, bounds.top + y);
+            bytes.extend_from_slice(image.pixel(claims, intact[y * width + x]));
+        }
+    }
+    Some(bytes)
+}
+
+fn part_a(fabric: &Fabric) -> usize {
+    fabric.overlap()
+}
+
+fn part_b(fabric: &Fabric) -> Result<usize> {
+    fabric
+        .squares()
+        .iter()
+        .find(|square| fabric.is_intact(square))
+        .map(|square| square.id)
+        .ok_or_else(|| AocError::unsolvable("every claim overlaps another claim"))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::Rng;
 
     #[test]
     fn parse() {
-        let square = Square::from("#1 @ 11,333: 400x4");
+        let square: Square = "#1 @ 11,333: 400x4".parse().unwrap();
         assert_eq!(square.id, 1);
         assert_eq!(square.from_left, 11);
         assert_eq!(square.from_top, 333);
@@ -88,8 +683,320 @@ mod tests {
         assert_eq!(square.depth, 4);
     }
 
+    fn squares(raw: &str) -> Vec<Square> {
+        parse_lines(raw).unwrap()
+    }
+
+    #[test]
+    fn example() {
+        let raw = "#1 @ 1,3: 4x4\n#2 @ 3,1: 4x4\n#3 @ 5,5: 2x2\n";
+        assert_eq!(solve(raw).unwrap(), Output::new(4, 3));
+
+        let fabric = Fabric::new(squares(raw)).unwrap();
+        assert_eq!(
+            fabric.bounds(),
+            Bounds {
+                left: 1,
+                top: 1,
+                right: 7,
+                bottom: 7
+            }
+        );
+        assert_eq!(fabric.claims(3, 3), 2);
+        assert_eq!(fabric.claims(0, 0), 0);
+        assert_eq!(fabric.claims(1000, 1000), 0);
+    }
+
+    #[test]
+    fn sparse() {
+        let raw = "#1 @ 0,0: 2x2\n#2 @ 1,1: 2x2\n#3 @ 100000,100000: 3x1\n";
+        let fabric = Fabric::new(squares(raw)).unwrap();
+        assert!(matches!(fabric.counts, Counts::Compressed { .. }));
+        assert_eq!(fabric.overlap(), 1);
+        assert_eq!(fabric.claims(100_002, 100_000), 1);
+        assert_eq!(part_b(&fabric).unwrap(), 3);
+    }
+
+    #[test]
+    fn large_claims() {
+        let raw = "#1 @ 0,0: 20000x20000\n#2 @ 1,1: 20000x20000\n#3 @ 50000,7: 10x10\n";
+        let fabric = Fabric::new(squares(raw)).unwrap();
+        assert_eq!(fabric.overlap(), 19_999 * 19_999);
+        assert_eq!(fabric.claims(0, 0), 1);
+        assert_eq!(fabric.claims(19_999, 19_999), 2);
+        assert_eq!(fabric.claims(20_000, 20_000), 1);
+        assert_eq!(fabric.claims(50_009, 16), 1);
+        assert_eq!(fabric.claims(50_010, 16), 0);
+        assert_eq!(part_b(&fabric).unwrap(), 3);
+        assert!(render(&fabric, Image::Pgm).is_none());
+
+        let raw = "#1 @ 0,0: 20000x20000\n#2 @ 1,1: 20000x20000\n";
+        assert!(solve(raw).is_err());
+    }
+
+    #[test]
+    fn scattered_claims() {
+        let mut raw: String = (0..2100)
+            .map(|id| format!("#{} @ {},{}: 1x1\n", id + 1, id * 10_000, id * 10_000))
+            .collect();
+        raw.push_str("#2101 @ 0,0: 3x3\n#2102 @ 2,2: 2x2\n");
+        let fabric = Fabric::new(squares(&raw)).unwrap();
+        assert!(matches!(fabric.counts, Counts::Sparse { .. }));
+        assert_eq!(fabric.overlap(), 2);
+        assert_eq!(fabric.claims(2, 2), 2);
+        assert_eq!(fabric.claims(10_000, 10_000), 1);
+        assert_eq!(fabric.claims(10_001, 10_000), 0);
+        assert_eq!(part_b(&fabric).unwrap(), 2);
+    }
+
+    #[test]
+    fn many_claims() {
+        let raw: String = (1..=300)
+            .map(|id| format!("#{} @ 2,2: 1x1\n", id))
+            .collect();
+        let fabric = Fabric::new(squares(&raw)).unwrap();
+        assert_eq!(fabric.claims(2, 2), 300);
+        assert_eq!(fabric.overlap(), 1);
+    }
+
+    #[test]
+    fn out_of_range() {
+        let err =
+            Fabric::with_size(squares("#1 @ 0,0: 2x2\n#2 @ 999,0: 2x1\n"), 1000, 1000).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "line 2: claim extends beyond the 1000x1000 fabric `#2 @ 999,0: 2x1`"
+        );
+
+        let huge = Square {
+            id: 1,
+            from_left: usize::MAX,
+            from_top: 0,
+            width: 1,
+            depth: 1,
+        };
+        assert!(matches!(
+            Fabric::new(vec![huge]),
+            Err(AocError::Parse { line: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn sweep_matches_grid() {
+        let mut rng = Rng::new(3);
+
+        for _ in 0..50 {
+            let squares: Vec<Square> = (1..=1 + rng.below(30))
+                .map(|id| Square {
+                    id,
+                    from_left: rng.below(50),
+                    from_top: rng.below(50),
+                    width: rng.below(20),
+                    depth: rng.below(20),
+                })
+                .collect();
+            assert_eq!(
+                overlap_area(&squares, Method::Sweep).unwrap(),
+                overlap_area(&squares, Method::Grid).unwrap(),
+                "{:?}",
+                squares
+            );
+        }
+    }
+
+    /// `squares` laid out per rectangle between claim edges whatever their bounds
+    fn compressed(squares: Vec<Square>) -> Fabric {
+        let fabric = Fabric::new(squares).unwrap();
+        let claims: Vec<Bounds> = fabric
+            .squares()
+            .iter()
+            .filter_map(Square::bounds)
+            .filter(|claimed| !claimed.is_empty())
+            .collect();
+        Fabric {
+            counts: Fabric::compress(&claims).unwrap(),
+            ..fabric
+        }
+    }
+
+    /// `squares` laid out without counts whatever their bounds
+    fn swept(squares: Vec<Square>) -> Fabric {
+        let overlap = overlap_area(&squares, Method::Sweep).unwrap();
+        Fabric {
+            counts: Counts::Sparse { overlap },
+            ..Fabric::new(squares).unwrap()
+        }
+    }
+
+    #[test]
+    fn intact_matches_across_layouts() {
+        let mut rng = Rng::new(17);
+
+        for _ in 0..50 {
+            let mut random_square = |id| Square {
+                id,
+                from_left: rng.below(50),
+                from_top: rng.below(50),
+                width: rng.below(20),
+                depth: rng.below(20),
+            };
+            let squares: Vec<Square> = (1..=30).map(&mut random_square).collect();
+            let others: Vec<Square> = (0..30).map(&mut random_square).collect();
+            let dense = Fabric::new(squares.clone()).unwrap();
+            let compressed = compressed(squares.clone());
+            let sparse = swept(squares);
+            for square in dense.squares().iter().chain(&others) {
+                let intact = dense.is_intact(square);
+                assert_eq!(compressed.is_intact(square), intact, "{}", square);
+                assert_eq!(sparse.is_intact(square), intact, "{}", square);
+            }
+            assert_eq!(sparse.overlap(), dense.overlap());
+            for _ in 0..20 {
+                let (x, y) = (rng.below(70), rng.below(70));
+                assert_eq!(sparse.claims(x, y), dense.claims(x, y));
+                assert_eq!(compressed.claims(x, y), dense.claims(x, y));
+            }
+        }
+        let far = Square {
+            id: 0,
+            from_left: 1000,
+            from_top: 1000,
+            width: 1,
+            depth: 1,
+        };
+        for fabric in &[
+            compressed(squares("#1 @ 0,0: 2x2\n#2 @ 5,5: 2x2\n")),
+            swept(squares("#1 @ 0,0: 2x2\n#2 @ 5,5: 2x2\n")),
+        ] {
+            assert!(!fabric.is_intact(&far));
+            assert!(fabric.is_intact(&squares("#3 @ 1,1: 1x1\n")[0]));
+        }
+    }
+
+    #[test]
+    fn sweep_huge() {
+        let raw = "#1 @ 0,0: 4000000x3000000\n#2 @ 1000000,1000000: 5000000x5000000\n\
+                   #3 @ 2000000,0: 1x1\n";
+        assert_eq!(
+            overlap_area(&squares(raw), Method::Sweep).unwrap(),
+            3_000_000 * 2_000_000 + 1
+        );
+    }
+
+    #[test]
+    fn full_sweep() {
+        let squares = squares(&load_input(INPUT).unwrap());
+        assert_eq!(overlap_area(&squares, Method::Sweep).unwrap(), 111935);
+    }
+
+    #[test]
+    fn overlaps() {
+        let squares = squares("#1 @ 1,3: 4x4\n#2 @ 3,1: 4x4\n#3 @ 5,5: 2x2\n#4 @ 0,0: 0x9\n");
+        let overlaps = Overlaps::new(&squares).unwrap();
+        let overlap = Bounds {
+            left: 3,
+            top: 3,
+            right: 5,
+            bottom: 5,
+        };
+        assert_eq!(
+            overlaps.of(0),
+            &[Intersection {
+                index: 1,
+                id: 2,
+                bounds: overlap
+            }]
+        );
+        assert_eq!(overlaps.of(1)[0].id, 1);
+        assert_eq!(overlaps.intact(), vec![3, 4]);
+        assert_eq!(overlaps.covering(4, 4), vec![1, 2]);
+        assert_eq!(overlaps.covering(6, 6), vec![3]);
+        assert_eq!(overlaps.covering(0, 0), Vec::<usize>::new());
+        assert_eq!(overlaps.covering(7, 7), Vec::<usize>::new());
+    }
+
+    #[test]
+    fn overlaps_match_brute_force() {
+        let mut rng = Rng::new(19);
+        let squares: Vec<Square> = (1..=200)
+            .map(|id| Square {
+                id,
+                from_left: rng.below(300),
+                from_top: rng.below(300),
+                width: rng.below(40),
+                depth: rng.below(40),
+            })
+            .collect();
+        let overlaps = Overlaps::new(&squares).unwrap();
+
+        for (idx, square) in squares.iter().enumerate() {
+            let claim = square.bounds().unwrap();
+            let mut expected: Vec<usize> = squares
+                .iter()
+                .enumerate()
+                .filter(|&(other, s)| {
+                    other != idx && claim.intersection(&s.bounds().unwrap()).is_some()
+                })
+                .map(|(other, _)| other)
+                .collect();
+            let mut found: Vec<usize> = overlaps.of(idx).iter().map(|i| i.index).collect();
+            expected.sort_unstable();
+            found.sort_unstable();
+            assert_eq!(found, expected, "{}", square);
+        }
+
+        let fabric = Fabric::new(squares.clone()).unwrap();
+        for &(x, y) in &[(0, 0), (150, 150), (37, 212), (299, 5)] {
+            assert_eq!(overlaps.covering(x, y).len() as u32, fabric.claims(x, y));
+        }
+    }
+
+    #[test]
+    fn full_overlaps() {
+        let squares = squares(&load_input(INPUT).unwrap());
+        assert_eq!(Overlaps::new(&squares).unwrap().intact(), vec![650]);
+    }
+
+    #[test]
+    fn render_example() {
+        let fabric = Fabric::new(squares("#1 @ 1,3: 4x4\n#2 @ 3,1: 4x4\n#3 @ 5,5: 2x2\n")).unwrap();
+        let pgm = render(&fabric, Image::Pgm).unwrap();
+        let header = b"P5\n6 6\n255\n";
+        assert_eq!(&pgm[..header.len()], header);
+
+        let pixels = &pgm[header.len()..];
+        assert_eq!(pixels.len(), 36);
+        // the top left corner is unclaimed, the bottom right is claim #3
+        assert_eq!((pixels[0], pixels[35]), (0, 255));
+        assert_eq!(pixels[2 * 6 + 2], 170);
+        assert_eq!(pixels[2 * 6], 85);
+
+        let ppm = render(&fabric, Image::Ppm).unwrap();
+        assert!(ppm.starts_with(b"P6\n6 6\n255\n"));
+        assert_eq!(ppm.len(), header.len() + 3 * 36);
+    }
+
+    #[test]
+    fn render_counts_match_part_a() {
+        let fabric = Fabric::new(squares(&load_input(INPUT).unwrap())).unwrap();
+        let ppm = render(&fabric, Image::Ppm).unwrap();
+        let header = format!(
+            "P6\n{} {}\n255\n",
+            fabric.bounds().width(),
+            fabric.bounds().depth()
+        );
+        let contested = ppm[header.len()..]
+            .chunks(3)
+            .filter(|&pixel| pixel == [220, 30, 30])
+            .count();
+        assert_eq!(contested, part_a(&fabric));
+
+        let sparse = Fabric::new(squares("#1 @ 0,0: 1x1\n#2 @ 9999999,9999999: 1x1\n")).unwrap();
+        assert_eq!(render(&sparse, Image::Pgm), None);
+    }
+
     #[test]
     fn full() {
-        assert_eq!(run(), Output { a: 111935, b: 650 });
+        assert_eq!(run().unwrap(), Output::new(111935, 650));
     }
 }
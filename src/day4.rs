@@ -1,89 +1,537 @@
-use super::{parse_input, Output};
+use super::{load_input, AocError, Output, Result, Solution};
 use regex::Regex;
 use std::cmp::Ordering;
-use std::collections::HashMap;
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::fmt::{self, Display};
+use std::ops::Bound::{Excluded, Included, Unbounded};
+use std::str::FromStr;
+use std::time::Duration;
 
 lazy_static! {
     static ref RE: Regex = Regex::new(r"#(\d+)").unwrap();
+    static ref TIMESTAMP: Regex =
+        Regex::new(r"^\s*(\d+)\s*-\s*(\d+)\s*-\s*(\d+)\s+(\d+)\s*:\s*(\d+)(?:\s*:\s*(\d+))?\s*$")
+            .unwrap();
 }
 const INPUT: &str = "day4.txt";
+const HOUR: usize = 60;
+const DAY: usize = 24 * HOUR;
+
+/// Which minutes of the day count as asleep
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum Hours {
+    /// Only the midnight hour 00:00–00:59, as in the puzzle
+    #[default]
+    Midnight,
+    /// Every minute of the day
+    All,
+}
+
+impl Hours {
+    /// Number of minutes of the day counted, counted minutes are `0..minutes()`
+    pub fn minutes(self) -> usize {
+        match self {
+            Hours::Midnight => HOUR,
+            Hours::All => DAY,
+        }
+    }
+}
+
+/// A half-open range of minutes `[start, end)` counted from midnight of the day it starts,
+/// `end` is past `DAY` if the range crosses midnight
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Asleep {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Asleep {
+    /// Asleep from minute of the day `start` until `end`, crossing midnight if `end` is
+    /// earlier than `start`
+    pub fn new(start: usize, end: usize) -> Self {
+        let end = if end < start { end + DAY } else { end };
+        Self { start, end }
+    }
+
+    /// Asleep from `since` until `until`, which may be days later
+    pub fn between(since: Timestamp, until: Timestamp) -> Self {
+        let start = since.minute_of_day();
+        let minutes = until.minutes().saturating_sub(since.minutes());
+        Self {
+            start,
+            end: start + minutes as usize,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+
+    /// Minutes of the day asleep, wrapping around at midnight
+    pub fn minutes(&self) -> impl Iterator<Item = usize> {
+        (self.start..self.end).map(|min| min % DAY)
+    }
+
+    /// Whether the minute of the day `minute` is asleep on any of the days of the range
+    pub fn covers(&self, minute: usize) -> bool {
+        (minute + DAY - self.start % DAY) % DAY < self.len()
+    }
+}
+
+/// One shift of a guard
+#[derive(Clone, Debug, PartialEq)]
+struct Night {
+    id: usize,
+    /// Midnight starting the midnight hour of the shift
+    date: Timestamp,
+    sleeps: Vec<Asleep>,
+}
+
+impl Night {
+    fn is_asleep(&self, minute: usize) -> bool {
+        self.sleeps.iter().any(|sleep| sleep.covers(minute))
+    }
+}
+
+/// Sleep statistics of a single guard over all of its shifts
+#[derive(Clone, Debug, PartialEq)]
+pub struct SleepReport {
+    pub id: usize,
+    /// Minutes asleep over all shifts
+    pub total: usize,
+    pub shifts: usize,
+    /// Number of shifts the guard was asleep at each counted minute, see `Hours::minutes`
+    pub histogram: Vec<usize>,
+    nights: Vec<Night>,
+}
+
+impl SleepReport {
+    fn new(id: usize, hours: Hours) -> Self {
+        Self {
+            id,
+            total: 0,
+            shifts: 0,
+            histogram: vec![0; hours.minutes()],
+            nights: Vec::new(),
+        }
+    }
+
+    /// Count the shift `night`, or stop counting it if `add` is false
+    fn tally(&mut self, night: &Night, add: bool) {
+        let counted = self.histogram.len();
+        let update = |n: &mut usize, by: usize| {
+            if add {
+                *n += by
+            } else {
+                *n -= by
+            }
+        };
+        update(&mut self.shifts, 1);
+        for sleep in &night.sleeps {
+            // every whole day covers each counted minute once, the rest at most once more
+            let (days, rest) = (sleep.len() / DAY, sleep.len() % DAY);
+            if days > 0 {
+                for n in &mut self.histogram {
+                    update(n, days);
+                }
+                update(&mut self.total, days * counted);
+            }
+            for min in (sleep.start..sleep.start + rest).map(|min| min % DAY) {
+                if min < counted {
+                    update(&mut self.histogram[min], 1);
+                    update(&mut self.total, 1);
+                }
+            }
+        }
+    }
+
+    /// The minute the guard was most often asleep together with the number of shifts, the
+    /// earliest minute on ties and `None` if the guard never slept
+    pub fn likeliest_minute(&self) -> Option<(usize, usize)> {
+        let (minute, &count) = self
+            .histogram
+            .iter()
+            .enumerate()
+            .rev()
+            .max_by_key(|&(_min, count)| count)?;
+        if count == 0 {
+            None
+        } else {
+            Some((minute, count))
+        }
+    }
+
+    /// The midnight hour of the guard's shifts as the timeline from the puzzle, `#` while asleep
+    pub fn timeline(&self) -> String {
+        timeline(self.nights.iter())
+    }
+}
+
+/// One line per night in the order of `nights` with a header of the minutes
+fn timeline<'a>(nights: impl Iterator<Item = &'a Night>) -> String {
+    let tens: String = (0..HOUR)
+        .map(|min| (b'0' + (min / 10) as u8) as char)
+        .collect();
+    let ones: String = (0..HOUR)
+        .map(|min| (b'0' + (min % 10) as u8) as char)
+        .collect();
+    let mut s = format!(
+        "Date   ID    Minute\n{:13}{}\n{:13}{}\n",
+        "", tens, "", ones
+    );
+    for night in nights {
+        let minutes: String = (0..HOUR)
+            .map(|min| if night.is_asleep(min) { '#' } else { '.' })
+            .collect();
+        s.push_str(&format!(
+            "{:02}-{:02}  {:<6}{}\n",
+            night.date.month(),
+            night.date.day(),
+            format!("#{}", night.id),
+            minutes
+        ));
+    }
+    s
+}
+
+/// How inconsistencies in a guard log are handled
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum Mode {
+    /// Skip the entries which don't fit, a sleep still open when the next shift begins is
+    /// dropped
+    #[default]
+    Strict,
+    /// Like `Strict` but a guard still asleep at the next shift wakes up when it begins
+    Lenient,
+}
+
+/// Something wrong with an entry of a guard log
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Issue {
+    /// The line isn't a log entry at all
+    Malformed(&'static str),
+    /// Fell asleep or woke up before the first shift
+    NoShift,
+    /// Fell asleep while already asleep
+    AlreadyAsleep,
+    /// Woke up without having fallen asleep
+    NotAsleep,
+    /// A shift began while the previous guard was asleep
+    AsleepAtShiftChange,
+    /// A shift began on the same night as the previous shift
+    OverlappingShift,
+    /// The log ends while the guard is asleep
+    AsleepAtEnd,
+}
+
+impl Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Issue::Malformed(reason) => write!(f, "{}", reason),
+            Issue::NoShift => write!(f, "no guard is on shift"),
+            Issue::AlreadyAsleep => write!(f, "guard is already asleep"),
+            Issue::NotAsleep => write!(f, "guard wakes up without falling asleep"),
+            Issue::AsleepAtShiftChange => write!(f, "shift begins while the last guard is asleep"),
+            Issue::OverlappingShift => write!(f, "shift begins on the night of the last shift"),
+            Issue::AsleepAtEnd => write!(f, "log ends while the guard is asleep"),
+        }
+    }
+}
+
+/// An issue together with the offending log line
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Diagnostic {
+    /// Line in the log, counted from 1
+    pub line: usize,
+    pub text: String,
+    pub issue: Issue,
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {} `{}`", self.line, self.issue, self.text)
+    }
+}
+
+impl From<Diagnostic> for AocError {
+    fn from(diagnostic: Diagnostic) -> Self {
+        AocError::parse(diagnostic.line, &diagnostic.text, diagnostic.issue)
+    }
+}
+
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+enum State {
+    #[default]
+    OffDuty,
+    Awake,
+    Asleep {
+        since: Timestamp,
+    },
+}
 
 #[derive(Debug, Default)]
-struct Guard {
-    last_operation: Operation,
-    id: Option<usize>,
-    slept_since: Option<usize>,
-    db: HashMap<usize, HashMap<usize, usize>>,
+pub struct Guard {
+    hours: Hours,
+    mode: Mode,
+    state: State,
+    nights: Vec<Night>,
 }
 
 impl Guard {
-    fn insert_in_db(&mut self, mins: usize, start: usize) {
-        for min in (start..=60).cycle().take(mins) {
-            self.db
-                .entry(self.id.expect("should have ID after shift; qed"))
-                .and_modify(|inner| *inner.entry(min).or_default() += 1)
-                .or_insert_with(|| {
-                    let mut map = HashMap::new();
-                    map.insert(min, 1);
-                    map
-                });
+    /// A guard log counting only the minutes in `hours`
+    pub fn new(hours: Hours) -> Self {
+        Self {
+            hours,
+            ..Self::default()
         }
     }
 
-    fn step(&mut self, next: &Transistion) {
-        match next.op {
-            Operation::Wakeup => {
-                if let Operation::Sleep = self.last_operation {
-                    let since = self
-                        .slept_since
-                        .take()
-                        .expect("sleep and wakeup are balanced; qed");
-                    let sleep_time = next
-                        .date
-                        .minute
-                        .checked_sub(since)
-                        .expect("wakeup time must be later sleep; qed");
-                    self.insert_in_db(sleep_time, since);
+    /// Apply the next entry of the log, adds what is wrong with it to `issues` if it doesn't
+    /// fit
+    fn step(&mut self, next: &Transistion, issues: &mut Vec<Issue>) {
+        match (next.op, self.state) {
+            (Operation::Shift(id), state) => {
+                if let State::Asleep { since } = state {
+                    if self.mode == Mode::Lenient {
+                        self.sleep(since, next.date);
+                    }
+                    issues.push(Issue::AsleepAtShiftChange);
                 }
+                let date = next
+                    .date
+                    .night()
+                    .expect("shifts are checked to have a night when parsed; qed");
+                if self.nights.last().is_some_and(|last| last.date == date) {
+                    issues.push(Issue::OverlappingShift);
+                }
+                self.nights.push(Night {
+                    id,
+                    date,
+                    sleeps: Vec::new(),
+                });
+                self.state = State::Awake;
             }
-            Operation::Shift(id) => {
-                self.id = Some(id);
+            (_, State::OffDuty) => issues.push(Issue::NoShift),
+            (Operation::Sleep, State::Awake) => {
+                self.state = State::Asleep { since: next.date };
             }
-            Operation::Sleep => {
-                if Operation::Sleep != self.last_operation {
-                    self.slept_since = Some(next.date.minute);
-                }
+            (Operation::Sleep, State::Asleep { .. }) => issues.push(Issue::AlreadyAsleep),
+            (Operation::Wakeup, State::Asleep { since }) => {
+                self.sleep(since, next.date);
+                self.state = State::Awake;
             }
-            _ => (),
-        };
-        self.last_operation = next.op;
+            (Operation::Wakeup, State::Awake) => issues.push(Issue::NotAsleep),
+        }
     }
 
-    fn most_minutes(&self) -> usize {
-        let (&id, minutes) = self
-            .db
-            .iter()
-            .max_by_key(|(_id, mins)| mins.values().sum::<usize>())
-            .unwrap();
-        let (&min, _cnt) = minutes.iter().max_by_key(|(_min, &cnt)| cnt).unwrap();
+    fn sleep(&mut self, since: Timestamp, until: Timestamp) {
+        self.nights
+            .last_mut()
+            .expect("should have a night after shift; qed")
+            .sleeps
+            .push(Asleep::between(since, until));
+    }
+
+    /// Sleep statistics of every guard, ordered by ID
+    pub fn reports(&self) -> Vec<SleepReport> {
+        let mut reports: Vec<SleepReport> = Vec::new();
+        let mut by_id = self.nights.iter().collect::<Vec<_>>();
+        by_id.sort_by_key(|night| night.id);
+
+        for night in by_id {
+            if reports.last().map(|report| report.id) != Some(night.id) {
+                reports.push(SleepReport::new(night.id, self.hours));
+            }
+            let report = reports.last_mut().expect("pushed above; qed");
+            report.tally(night, true);
+            report.nights.push(night.clone());
+        }
+        reports
+    }
+
+    /// Sleep statistics of the guard with `id`, `None` if it never had a shift
+    pub fn report(&self, id: usize) -> Option<SleepReport> {
+        self.reports().into_iter().find(|report| report.id == id)
+    }
 
-        id * min
+    /// The midnight hour of all shifts as the timeline from the puzzle, in the order they
+    /// happened
+    pub fn timeline(&self) -> String {
+        timeline(self.nights.iter())
     }
 
-    fn most_sleeps(&self) -> usize {
-        let mut max = 0;
-        let mut id = 0;
-        let mut min = 0;
+    fn most_minutes(&self) -> Result<usize> {
+        most_minutes(&self.reports())
+    }
+
+    fn most_sleeps(&self) -> Result<usize> {
+        most_sleeps(&self.reports())
+    }
+}
+
+/// ID of the guard asleep the most minutes times its likeliest minute
+fn most_minutes<'a>(reports: impl IntoIterator<Item = &'a SleepReport>) -> Result<usize> {
+    let report = reports
+        .into_iter()
+        .filter(|report| report.total > 0)
+        .max_by_key(|report| report.total)
+        .ok_or_else(|| AocError::unsolvable("no guard fell asleep"))?;
+    let (min, _cnt) = report
+        .likeliest_minute()
+        .expect("guards with minutes asleep have a likeliest minute; qed");
+
+    Ok(report.id * min)
+}
+
+/// ID of the guard most often asleep at the same minute times that minute
+fn most_sleeps<'a>(reports: impl IntoIterator<Item = &'a SleepReport>) -> Result<usize> {
+    reports
+        .into_iter()
+        .filter_map(|report| {
+            report
+                .likeliest_minute()
+                .map(|(min, cnt)| (cnt, report.id * min))
+        })
+        .max_by_key(|&(cnt, _answer)| cnt)
+        .map(|(_cnt, answer)| answer)
+        .ok_or_else(|| AocError::unsolvable("no guard fell asleep"))
+}
+
+/// A guard log which grows over time with entries arriving in any order
+///
+/// The entries are kept ordered by time and every new entry only replays the shift it falls
+/// in, so the statistics are always up to date without reprocessing the whole history.
+#[derive(Debug, Default)]
+pub struct LogStream {
+    hours: Hours,
+    mode: Mode,
+    /// Entries by time and then by arrival
+    entries: BTreeMap<(Timestamp, usize), Operation>,
+    /// Nights by the entry of their shift
+    nights: BTreeMap<(Timestamp, usize), Night>,
+    reports: BTreeMap<usize, SleepReport>,
+    lines: usize,
+}
 
-        for (&c_id, minutes) in &self.db {
-            let (&c_min, &cand) = minutes.iter().max_by_key(|&(_min, cnt)| cnt).unwrap();
-            if cand > max {
-                id = c_id;
-                max = cand;
-                min = c_min;
+impl LogStream {
+    pub fn new(hours: Hours, mode: Mode) -> Self {
+        Self {
+            hours,
+            mode,
+            ..Self::default()
+        }
+    }
+
+    /// Add the entry `line`, lines are numbered in the order they are pushed for errors
+    pub fn push(&mut self, line: &str) -> Result<()> {
+        self.lines += 1;
+        let t: Transistion = line
+            .parse()
+            .map_err(|reason| AocError::parse(self.lines, line, reason))?;
+        self.insert(t);
+        Ok(())
+    }
+
+    /// Add every line of `raw`, stops at the first malformed line
+    pub fn extend(&mut self, raw: &str) -> Result<()> {
+        raw.lines().try_for_each(|line| self.push(line))
+    }
+
+    fn insert(&mut self, t: Transistion) {
+        let key = (t.date, self.entries.len());
+        self.entries.insert(key, t.op);
+
+        let is_shift =
+            |(_, op): &(&(Timestamp, usize), &Operation)| matches!(op, Operation::Shift(_));
+        // the shift the entry falls in, a new shift also cuts it short
+        let from = self
+            .entries
+            .range(..key)
+            .rev()
+            .find(is_shift)
+            .map_or(key, |(&key, _)| key);
+        let to = self
+            .entries
+            .range((Excluded(key), Unbounded))
+            .find(is_shift)
+            .map(|(&key, _)| key);
+        let range = (Included(from), to.map_or(Unbounded, Included));
+
+        let stale: Vec<_> = self.nights.range(range).map(|(&key, _)| key).collect();
+        for key in stale {
+            if Some(key) == to {
+                continue;
+            }
+            let night = self.nights.remove(&key).expect("key was just found; qed");
+            let report = self
+                .reports
+                .get_mut(&night.id)
+                .expect("nights are counted; qed");
+            report.tally(&night, false);
+            if report.shifts == 0 {
+                self.reports.remove(&night.id);
+            }
+        }
+
+        // the next shift is replayed too so that an open sleep is handled like in `Guard`
+        let mut guard = Guard {
+            hours: self.hours,
+            mode: self.mode,
+            ..Guard::default()
+        };
+        let (mut shifts, mut issues) = (Vec::new(), Vec::new());
+        for (&key, &op) in self.entries.range(range) {
+            guard.step(&Transistion { date: key.0, op }, &mut issues);
+            if let Operation::Shift(_) = op {
+                shifts.push(key);
+            }
+        }
+        let hours = self.hours;
+        for (key, night) in shifts.into_iter().zip(guard.nights) {
+            if Some(key) == to {
+                break;
             }
+            self.reports
+                .entry(night.id)
+                .or_insert_with(|| SleepReport::new(night.id, hours))
+                .tally(&night, true);
+            self.nights.insert(key, night);
         }
-        id * min
+    }
+
+    /// Sleep statistics of every guard so far, ordered by ID
+    pub fn reports(&self) -> Vec<SleepReport> {
+        self.reports
+            .keys()
+            .filter_map(|&id| self.report(id))
+            .collect()
+    }
+
+    /// Sleep statistics of the guard with `id` so far, `None` if it had no shift yet
+    pub fn report(&self, id: usize) -> Option<SleepReport> {
+        let mut report = self.reports.get(&id)?.clone();
+        report.nights = self
+            .nights
+            .values()
+            .filter(|night| night.id == id)
+            .cloned()
+            .collect();
+        Some(report)
+    }
+
+    /// The puzzle's first strategy on the entries so far
+    pub fn most_minutes(&self) -> Result<usize> {
+        most_minutes(self.reports.values())
+    }
+
+    /// The puzzle's second strategy on the entries so far
+    pub fn most_sleeps(&self) -> Result<usize> {
+        most_sleeps(self.reports.values())
     }
 }
 
@@ -92,27 +540,216 @@ enum Operation {
     Sleep,
     Wakeup,
     Shift(usize),
-    Noop,
 }
 
-impl Default for Operation {
-    fn default() -> Self {
-        Operation::Noop
+/// A validated date and time with second precision, ordered chronologically
+#[derive(Copy, Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Timestamp {
+    year: i32,
+    month: u32,
+    day: u32,
+    hour: u32,
+    minute: u32,
+    second: u32,
+}
+
+impl Timestamp {
+    pub fn new(
+        year: i32,
+        month: u32,
+        day: u32,
+        hour: u32,
+        minute: u32,
+        second: u32,
+    ) -> std::result::Result<Self, &'static str> {
+        if !(1..=12).contains(&month) {
+            return Err("month out of range");
+        } else if day == 0 || day > days_in_month(year, month) {
+            return Err("day out of range");
+        } else if hour >= 24 {
+            return Err("hour out of range");
+        } else if minute >= 60 {
+            return Err("minute out of range");
+        } else if second >= 60 {
+            return Err("second out of range");
+        }
+        Ok(Self {
+            year,
+            month,
+            day,
+            hour,
+            minute,
+            second,
+        })
+    }
+
+    /// The time `seconds` after 1970-01-01 00:00:00, which may be negative
+    ///
+    /// Panics if the year doesn't fit in an `i32`, see `checked_from_seconds`.
+    pub fn from_seconds(seconds: i64) -> Self {
+        Self::checked_from_seconds(seconds).expect("year out of range")
+    }
+
+    /// The time `seconds` after 1970-01-01 00:00:00, `None` if the year doesn't fit in an
+    /// `i32`
+    pub fn checked_from_seconds(seconds: i64) -> Option<Self> {
+        let (days, secs) = (
+            seconds.div_euclid(86_400),
+            seconds.rem_euclid(86_400) as u32,
+        );
+        let (year, month, day) = civil_from_days(days)?;
+        Some(Self {
+            year,
+            month,
+            day,
+            hour: secs / 3600,
+            minute: secs / 60 % 60,
+            second: secs % 60,
+        })
+    }
+
+    /// Seconds since 1970-01-01 00:00:00, negative before
+    pub fn to_seconds(&self) -> i64 {
+        days_from_civil(self.year, self.month, self.day) * 86_400
+            + i64::from(self.hour * 3600 + self.minute * 60 + self.second)
+    }
+
+    /// Whole minutes since 1970-01-01 00:00
+    fn minutes(&self) -> i64 {
+        self.to_seconds().div_euclid(60)
+    }
+
+    pub fn year(&self) -> i32 {
+        self.year
+    }
+
+    pub fn month(&self) -> u32 {
+        self.month
+    }
+
+    pub fn day(&self) -> u32 {
+        self.day
+    }
+
+    pub fn hour(&self) -> u32 {
+        self.hour
+    }
+
+    pub fn minute(&self) -> u32 {
+        self.minute
+    }
+
+    pub fn second(&self) -> u32 {
+        self.second
+    }
+
+    /// Minutes since midnight
+    pub fn minute_of_day(&self) -> usize {
+        (self.hour * 60 + self.minute) as usize
+    }
+
+    pub fn checked_add(&self, duration: Duration) -> Option<Self> {
+        let seconds = i64::try_from(duration.as_secs()).ok()?;
+        Self::checked_from_seconds(self.to_seconds().checked_add(seconds)?)
+    }
+
+    /// Time elapsed since `earlier`, `None` if `earlier` is later
+    pub fn duration_since(&self, earlier: Timestamp) -> Option<Duration> {
+        let seconds = self.to_seconds() - earlier.to_seconds();
+        u64::try_from(seconds).ok().map(Duration::from_secs)
+    }
+
+    /// Midnight starting the midnight hour a shift beginning at this time covers, today's
+    /// unless the time is already past the midnight hour, `None` if tomorrow's year doesn't
+    /// fit in an `i32`
+    pub fn night(&self) -> Option<Timestamp> {
+        let midnight = Self {
+            hour: 0,
+            minute: 0,
+            second: 0,
+            ..*self
+        };
+        if self.hour == 0 {
+            Some(midnight)
+        } else {
+            Self::checked_from_seconds(midnight.to_seconds() + 86_400)
+        }
+    }
+}
+
+impl Display for Timestamp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{:04}-{:02}-{:02} {:02}:{:02}",
+            self.year, self.month, self.day, self.hour, self.minute
+        )?;
+        if self.second != 0 {
+            write!(f, ":{:02}", self.second)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Timestamp {
+    type Err = &'static str;
+
+    /// Parse `year-month-day hour:minute[:second]`, allowing whitespace around the separators
+    fn from_str(input: &str) -> std::result::Result<Self, Self::Err> {
+        let caps = TIMESTAMP.captures(input).ok_or("bad timestamp")?;
+        let field = |idx: usize| -> std::result::Result<u32, Self::Err> {
+            caps.get(idx)
+                .map_or(Ok(0), |m| m.as_str().parse().map_err(|_| "bad timestamp"))
+        };
+        let year = caps[1].parse().map_err(|_| "bad timestamp")?;
+        Self::new(year, field(2)?, field(3)?, field(4)?, field(5)?, field(6)?)
+    }
+}
+
+fn days_in_month(year: i32, month: u32) -> u32 {
+    let leap = year.rem_euclid(4) == 0 && (year.rem_euclid(100) != 0 || year.rem_euclid(400) == 0);
+    match month {
+        2 if leap => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
     }
 }
 
-#[derive(Copy, Clone, Debug, Eq, PartialEq, PartialOrd, Ord)]
-struct Date {
-    year: usize,
-    month: usize,
-    day: usize,
-    hour: usize,
-    minute: usize,
+/// Days since 1970-01-01 in the proleptic Gregorian calendar, after Howard Hinnant's
+/// `days_from_civil`
+fn days_from_civil(year: i32, month: u32, day: u32) -> i64 {
+    let year = i64::from(year) - i64::from(month <= 2);
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month_from_march = i64::from((month + 9) % 12);
+    let day_of_year = (153 * month_from_march + 2) / 5 + i64::from(day) - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// Inverse of `days_from_civil`
+fn civil_from_days(days: i64) -> Option<(i32, u32, u32)> {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_from_march = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month_from_march + 2) / 5 + 1) as u32;
+    let month = if month_from_march < 10 {
+        month_from_march + 3
+    } else {
+        month_from_march - 9
+    } as u32;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    Some((i32::try_from(year).ok()?, month, day))
 }
 
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
 struct Transistion {
-    pub date: Date,
+    pub date: Timestamp,
     pub op: Operation,
 }
 
@@ -128,86 +765,141 @@ impl PartialOrd for Transistion {
     }
 }
 
-impl From<&str> for Transistion {
-    fn from(input: &str) -> Self {
-        let (timestamp, action) = input.split_at(19);
-
-        let date = Date {
-            year: timestamp
-                .get(1..5)
-                .expect("well-formed input")
-                .parse()
-                .expect("Year parse"),
-            month: timestamp
-                .get(6..8)
-                .expect("well-formed input")
-                .parse()
-                .expect("Month parse"),
-            day: timestamp
-                .get(9..11)
-                .expect("well-formed input")
-                .parse()
-                .expect("Day parse"),
-            hour: timestamp
-                .get(12..14)
-                .expect("well-formed input")
-                .parse()
-                .expect("Hour parse"),
-            minute: timestamp
-                .get(15..17)
-                .expect("well-formed input")
-                .parse()
-                .expect("Minute parse"),
-        };
+impl FromStr for Transistion {
+    type Err = &'static str;
+
+    fn from_str(input: &str) -> std::result::Result<Self, Self::Err> {
+        let (timestamp, action) = input
+            .trim_start()
+            .strip_prefix('[')
+            .and_then(|rest| rest.split_once(']'))
+            .ok_or("bad timestamp")?;
+        let date: Timestamp = timestamp.parse()?;
+        let action = action.trim_start();
 
         let op = if action.starts_with("Guard") {
             let id = RE
                 .captures(action)
-                .expect("well-formed input")
-                .get(1)
-                .map_or("no match", |m| m.as_str())
-                .parse()
-                .expect("malformed id");
+                .and_then(|caps| caps[1].parse().ok())
+                .ok_or("malformed guard id")?;
+            date.night().ok_or("shift begins after the last night")?;
             Operation::Shift(id)
-        } else if date.hour != 0 || date.minute >= 60 {
-            Operation::Noop
         } else if action.starts_with("falls asleep") {
             Operation::Sleep
         } else if action.contains("wakes up") {
             Operation::Wakeup
         } else {
-            unreachable!();
+            return Err("unknown action");
         };
 
-        Self { date, op }
+        Ok(Self { date, op })
     }
 }
 
-pub fn run() -> Output<usize, usize> {
-    let mut transistions: Vec<Transistion> = parse_input(INPUT).lines().map(Into::into).collect();
-    transistions.sort();
-    let mut guard = Guard::default();
-    for t in transistions.iter() {
-        guard.step(t);
+pub struct Day4;
+
+impl Solution for Day4 {
+    type Input = Guard;
+    type A = usize;
+    type B = usize;
+
+    fn parse(&self, raw: &str) -> Result<Self::Input> {
+        replay(raw, Hours::Midnight)
+    }
+
+    fn part_a(&self, input: &Self::Input) -> Result<Self::A> {
+        part_a(input)
+    }
+
+    fn part_b(&self, input: &Self::Input) -> Result<Self::B> {
+        part_b(input)
+    }
+}
+
+/// Replay the log `raw` in chronological order, counting the minutes asleep in `hours`,
+/// lines which aren't log entries are an error and the entries which don't fit are handled
+/// like in `Mode::Lenient`
+pub fn replay(raw: &str, hours: Hours) -> Result<Guard> {
+    let (guard, diagnostics) = validate(raw, hours, Mode::Lenient);
+    let malformed = diagnostics
+        .into_iter()
+        .find(|diagnostic| matches!(diagnostic.issue, Issue::Malformed(_)));
+    match malformed {
+        Some(diagnostic) => Err(diagnostic.into()),
+        None => Ok(guard),
     }
+}
+
+/// Replay the log `raw` like `replay` but the first entry which doesn't fit is an error too
+pub fn replay_checked(raw: &str, hours: Hours) -> Result<Guard> {
+    let (guard, diagnostics) = validate(raw, hours, Mode::Strict);
+    match diagnostics.into_iter().next() {
+        Some(diagnostic) => Err(diagnostic.into()),
+        None => Ok(guard),
+    }
+}
+
+/// Replay the log `raw` in chronological order, skipping the lines and entries which don't
+/// fit, returns them ordered by line together with the guards
+pub fn validate(raw: &str, hours: Hours, mode: Mode) -> (Guard, Vec<Diagnostic>) {
+    let mut diagnostics = Vec::new();
+    let mut transistions = Vec::new();
+    for (idx, line) in raw.lines().enumerate() {
+        match line.parse::<Transistion>() {
+            Ok(t) => transistions.push((t, idx)),
+            Err(reason) => diagnostics.push((idx, Issue::Malformed(reason))),
+        }
+    }
+    transistions.sort();
 
-    Output {
-        a: part_a(&guard),
-        b: part_b(&guard),
+    let mut guard = Guard {
+        hours,
+        mode,
+        ..Guard::default()
+    };
+    let mut issues = Vec::new();
+    for &(ref t, idx) in &transistions {
+        guard.step(t, &mut issues);
+        diagnostics.extend(issues.drain(..).map(|issue| (idx, issue)));
     }
+    if let (State::Asleep { .. }, Some(&(_, idx))) = (guard.state, transistions.last()) {
+        diagnostics.push((idx, Issue::AsleepAtEnd));
+    }
+
+    let lines: Vec<&str> = raw.lines().collect();
+    diagnostics.sort_by_key(|&(idx, _)| idx);
+    let diagnostics = diagnostics
+        .into_iter()
+        .map(|(idx, issue)| Diagnostic {
+            line: idx + 1,
+            text: lines[idx].to_string(),
+            issue,
+        })
+        .collect();
+    (guard, diagnostics)
 }
 
-fn part_a(guard: &Guard) -> usize {
+/// Solve both parts for the puzzle input `input`
+pub fn solve(input: &str) -> Result<Output<usize, usize>> {
+    Day4.solve(input)
+}
+
+pub fn run() -> Result<Output<usize, usize>> {
+    solve(&load_input(INPUT)?)
+}
+
+fn part_a(guard: &Guard) -> Result<usize> {
     guard.most_minutes()
 }
 
-fn part_b(guard: &Guard) -> usize {
+fn part_b(guard: &Guard) -> Result<usize> {
     guard.most_sleeps()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::Rng;
 
     #[test]
     fn a_b() {
@@ -231,21 +923,439 @@ mod tests {
             "[1518-11-05 00:55] wakes up",
         ]
         .into_iter()
-        .map(Into::into)
+        .map(|line| line.parse().unwrap())
         .collect();
         input.sort();
 
         let guard = input.iter().fold(Guard::default(), |mut guard, t| {
-            guard.step(t);
+            let mut issues = Vec::new();
+            guard.step(t, &mut issues);
+            assert!(issues.is_empty());
             guard
         });
 
-        assert_eq!(part_a(&guard), 240);
-        assert_eq!(part_b(&guard), 4455);
+        assert_eq!(part_a(&guard).unwrap(), 240);
+        assert_eq!(part_b(&guard).unwrap(), 4455);
+
+        let reports = guard.reports();
+        assert_eq!(
+            reports.iter().map(|r| r.id).collect::<Vec<_>>(),
+            vec![10, 99]
+        );
+        assert_eq!((reports[0].total, reports[0].shifts), (50, 2));
+        assert_eq!(reports[0].likeliest_minute(), Some((24, 2)));
+        assert_eq!((reports[1].total, reports[1].shifts), (30, 3));
+        assert_eq!(reports[1].likeliest_minute(), Some((45, 3)));
+        assert_eq!(
+            reports[1].histogram[36..=46],
+            [1, 1, 1, 1, 2, 2, 2, 2, 2, 3, 2]
+        );
+
+        assert_eq!(
+            guard.timeline(),
+            "Date   ID    Minute
+             000000000011111111112222222222333333333344444444445555555555
+             012345678901234567890123456789012345678901234567890123456789
+11-01  #10   .....####################.....#########################.....
+11-02  #99   ........................................##########..........
+11-03  #10   ........................#####...............................
+11-04  #99   ....................................##########..............
+11-05  #99   .............................................##########.....
+"
+        );
+        assert!(guard.report(99).unwrap().timeline().ends_with(
+            "11-05  #99   .............................................##########.....\n"
+        ));
+        assert_eq!(guard.report(1), None);
+    }
+
+    fn at(raw: &str) -> Timestamp {
+        raw.parse().unwrap()
+    }
+
+    #[test]
+    fn night_after_midnight() {
+        assert_eq!(at("1518-11-01 00:58").night(), Some(at("1518-11-01 00:00")));
+        assert_eq!(at("1518-11-01 23:58").night(), Some(at("1518-11-02 00:00")));
+        assert_eq!(at("1518-04-30 23:58").night(), Some(at("1518-05-01 00:00")));
+        assert_eq!(at("1518-02-28 23:58").night(), Some(at("1518-03-01 00:00")));
+        assert_eq!(at("1600-02-28 23:58").night(), Some(at("1600-02-29 00:00")));
+        assert_eq!(at("1518-12-31 23:58").night(), Some(at("1519-01-01 00:00")));
+        assert_eq!(at("2147483647-12-31 23:58").night(), None);
+        assert_eq!(
+            at("2147483647-12-31 00:58").night(),
+            Some(at("2147483647-12-31 00:00"))
+        );
+
+        let log = "[2147483647-12-31 23:58] Guard #1 begins shift";
+        assert_eq!(
+            solve(log).unwrap_err().to_string(),
+            AocError::parse(1, log, "shift begins after the last night").to_string()
+        );
+    }
+
+    #[test]
+    fn timestamp() {
+        assert_eq!(
+            at("  1518 - 3 -01   23 : 58 : 07 "),
+            Timestamp::new(1518, 3, 1, 23, 58, 7).unwrap()
+        );
+        assert_eq!(at("1518-03-01 23:58:07").to_string(), "1518-03-01 23:58:07");
+        assert_eq!(at("1518-03-01 00:05").to_string(), "1518-03-01 00:05");
+        assert_eq!(at("1970-01-01 00:01").to_seconds(), 60);
+
+        assert_eq!(
+            "1600-02-29 00:00".parse::<Timestamp>().map(|t| t.day()),
+            Ok(29)
+        );
+        assert_eq!(
+            "1700-02-29 00:00".parse::<Timestamp>(),
+            Err("day out of range")
+        );
+        assert_eq!(
+            "1518-04-31 00:00".parse::<Timestamp>(),
+            Err("day out of range")
+        );
+        assert_eq!(
+            "1518-13-01 00:00".parse::<Timestamp>(),
+            Err("month out of range")
+        );
+        assert_eq!(
+            "1518-01-01 24:00".parse::<Timestamp>(),
+            Err("hour out of range")
+        );
+        assert_eq!(
+            "1518-01-01 00:60".parse::<Timestamp>(),
+            Err("minute out of range")
+        );
+        assert_eq!(
+            "1518-01-01 00:00:60".parse::<Timestamp>(),
+            Err("second out of range")
+        );
+        assert_eq!("1518-01-01".parse::<Timestamp>(), Err("bad timestamp"));
+    }
+
+    #[test]
+    fn timestamp_arithmetic() {
+        let before = at("1518-02-28 23:58");
+        let after = before.checked_add(Duration::from_secs(3 * 60)).unwrap();
+        assert_eq!(after, at("1518-03-01 00:01"));
+        assert_eq!(after.duration_since(before), Some(Duration::from_secs(180)));
+        assert_eq!(before.duration_since(after), None);
+        assert_eq!(after.minute_of_day(), 1);
+
+        for &seconds in &[-14_000_000_000, -86_401, -1, 0, 59, 951_782_400] {
+            assert_eq!(Timestamp::from_seconds(seconds).to_seconds(), seconds);
+        }
+        assert_eq!(Timestamp::from_seconds(951_782_400), at("2000-02-29 00:00"));
+
+        let start = at("1518-11-01 00:00");
+        assert_eq!(
+            start.checked_add(Duration::from_secs(i64::MAX as u64 / 2)),
+            None
+        );
+        assert_eq!(start.checked_add(Duration::from_secs(u64::MAX)), None);
+        assert_eq!(Timestamp::checked_from_seconds(i64::MIN), None);
+        let last = Timestamp::new(i32::MAX, 12, 31, 23, 59, 59).unwrap();
+        assert_eq!(
+            Timestamp::checked_from_seconds(last.to_seconds()),
+            Some(last)
+        );
+        assert_eq!(last.checked_add(Duration::from_secs(1)), None);
+
+        let shift = Asleep::between(at("1518-03-01 23:58"), at("1518-03-02 00:02"));
+        assert_eq!((shift.start, shift.len()), (DAY - 2, 4));
+    }
+
+    #[test]
+    fn tolerant_log_lines() {
+        let t: Transistion = "  [1518-11-01  23:58:30]   Guard #10 begins shift"
+            .parse()
+            .unwrap();
+        assert_eq!(t.date, Timestamp::new(1518, 11, 1, 23, 58, 30).unwrap());
+        assert_eq!(t.op, Operation::Shift(10));
+        assert_eq!(
+            "[1518-02-30 00:00] wakes up".parse::<Transistion>(),
+            Err("day out of range")
+        );
+        assert_eq!(
+            "1518-02-01 00:00 wakes up".parse::<Transistion>(),
+            Err("bad timestamp")
+        );
+    }
+
+    #[test]
+    fn asleep() {
+        let across_hour = Asleep::new(50, 70);
+        assert_eq!(across_hour.len(), 20);
+        let across_midnight = Asleep::new(DAY - 5, 10);
+        assert_eq!(across_midnight.len(), 15);
+        assert_eq!(across_midnight.minutes().nth(4), Some(DAY - 1));
+        assert_eq!(across_midnight.minutes().nth(5), Some(0));
+        assert!(Asleep::new(30, 30).is_empty());
+    }
+
+    #[test]
+    fn sleep_across_hour_and_midnight() {
+        let log = "[1518-03-01 23:50] Guard #7 begins shift
+[1518-03-01 23:55] falls asleep
+[1518-03-02 00:05] wakes up
+[1518-03-02 00:50] falls asleep
+[1518-03-02 01:10] wakes up";
+        let midnight = replay(log, Hours::Midnight).unwrap().report(7).unwrap();
+        assert_eq!(midnight.total, 15);
+        assert_eq!(midnight.histogram.len(), HOUR);
+        assert_eq!(midnight.histogram[..6], [1, 1, 1, 1, 1, 0]);
+        assert_eq!(midnight.histogram[50..], [1; 10]);
+
+        let all = replay(log, Hours::All).unwrap().report(7).unwrap();
+        assert_eq!(all.total, 30);
+        assert_eq!(all.histogram[DAY - 5..], [1; 5]);
+        assert_eq!(all.histogram[HOUR..HOUR + 10], [1; 10]);
+        assert_eq!(all.histogram[HOUR + 10], 0);
+    }
+
+    #[test]
+    fn sleep_over_many_years() {
+        let log = "[1518-03-01 00:00] Guard #1 begins shift
+[1518-03-01 00:05] falls asleep
+[9518-03-01 00:10] wakes up";
+        let days = 20 * 146_097;
+        let guard = replay(log, Hours::Midnight).unwrap();
+        let report = guard.report(1).unwrap();
+        assert_eq!(report.total, days * HOUR + 5);
+        assert_eq!(report.likeliest_minute(), Some((5, days + 1)));
+        assert_eq!(report.histogram[4], days);
+        assert!(guard
+            .timeline()
+            .ends_with(&format!("03-01  #1    {}\n", "#".repeat(HOUR))));
+
+        let all = replay(log, Hours::All).unwrap().report(1).unwrap();
+        assert_eq!(all.total, days * DAY + 5);
+        assert_eq!(all.histogram[DAY - 1], days);
+
+        let sleep = Asleep::new(DAY - 10, 10);
+        assert!(sleep.covers(DAY - 10) && sleep.covers(0) && sleep.covers(9));
+        assert!(!sleep.covers(10) && !sleep.covers(DAY - 11));
+        assert!(!Asleep::new(5, 5).covers(5));
+    }
+
+    /// Guard ID and the sleeps `(start, end)` of one night
+    type Sleeps = (usize, Vec<(usize, usize)>);
+
+    /// Random logs of shifts starting shortly before or after midnight with sleeps that may
+    /// cross the hour or midnight, together with the sleeps of every night
+    fn random_log(seed: u64) -> (String, Vec<Sleeps>) {
+        let mut rng = Rng::new(seed);
+        let mut lines = Vec::new();
+        let mut nights = Vec::new();
+        // minutes are counted from midnight before the day of the shift
+        let mut line = |day: usize, at: usize, action: String| {
+            lines.push(format!(
+                "[1518-03-{:02} {:02}:{:02}] {}",
+                day + at / DAY,
+                at % DAY / HOUR,
+                at % HOUR,
+                action
+            ))
+        };
+
+        for day in 1..28 {
+            let id = 1 + rng.below(4);
+            let mut at = DAY - 10 + rng.below(20);
+            line(day, at, format!("Guard #{} begins shift", id));
+            let mut sleeps = Vec::new();
+            for _ in 0..rng.below(4) {
+                let start = at + 1 + rng.below(90);
+                at = start + 1 + rng.below(120);
+                line(day, start, "falls asleep".to_string());
+                line(day, at, "wakes up".to_string());
+                sleeps.push((start, at));
+            }
+            nights.push((id, sleeps));
+        }
+        (lines.join("\n"), nights)
+    }
+
+    #[test]
+    fn totals_are_sum_of_intervals() {
+        for seed in 0..50 {
+            let (log, nights) = random_log(seed);
+            for &hours in &[Hours::Midnight, Hours::All] {
+                let guard = replay(&log, hours).unwrap();
+                for report in guard.reports() {
+                    let sleeps = nights
+                        .iter()
+                        .filter(|(id, _)| *id == report.id)
+                        .flat_map(|(_, sleeps)| sleeps);
+                    let expected: usize = match hours {
+                        Hours::All => sleeps.map(|(start, end)| end - start).sum(),
+                        Hours::Midnight => sleeps
+                            .flat_map(|&(start, end)| start..end)
+                            .filter(|min| min % DAY < HOUR)
+                            .count(),
+                    };
+                    assert_eq!(report.total, expected, "{}", log);
+                    assert_eq!(report.histogram.iter().sum::<usize>(), expected);
+                    assert_eq!(
+                        report.shifts,
+                        nights.iter().filter(|(id, _)| *id == report.id).count()
+                    );
+                }
+            }
+        }
+    }
+
+    const MESSY: &str = "[1518-03-01 00:05] falls asleep
+[1518-03-01 23:58] Guard #7 begins shift
+[1518-03-02 00:10] falls asleep
+[1518-03-02 00:12] falls asleep
+[1518-03-02 00:20] wakes up
+[1518-03-02 00:25] wakes up
+[1518-03-02 00:30] falls asleep
+[1518-03-02 00:40] Guard #8 begins shift
+[1518-03-02 00:45] dances
+[1518-03-02 00:50] falls asleep";
+
+    #[test]
+    fn validate_reports_every_issue() {
+        let (guard, diagnostics) = validate(MESSY, Hours::Midnight, Mode::Strict);
+        let issues: Vec<(usize, Issue)> = diagnostics
+            .iter()
+            .map(|d| (d.line, d.issue.clone()))
+            .collect();
+        assert_eq!(
+            issues,
+            vec![
+                (1, Issue::NoShift),
+                (4, Issue::AlreadyAsleep),
+                (6, Issue::NotAsleep),
+                (8, Issue::AsleepAtShiftChange),
+                (8, Issue::OverlappingShift),
+                (9, Issue::Malformed("unknown action")),
+                (10, Issue::AsleepAtEnd),
+            ]
+        );
+        assert_eq!(
+            diagnostics[2].to_string(),
+            "line 6: guard wakes up without falling asleep `[1518-03-02 00:25] wakes up`"
+        );
+        // the sleep open at the shift change is dropped
+        assert_eq!(guard.report(7).unwrap().total, 10);
+
+        let err = replay_checked(MESSY, Hours::Midnight).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "line 1: no guard is on shift `[1518-03-01 00:05] falls asleep`"
+        );
+    }
+
+    #[test]
+    fn shifts_a_year_apart_dont_overlap() {
+        let log = "[1518-11-01 00:00] Guard #1 begins shift
+[1519-11-01 00:00] Guard #2 begins shift
+[1519-10-31 23:50] Guard #3 begins shift";
+        let (guard, diagnostics) = validate(log, Hours::Midnight, Mode::Strict);
+        assert_eq!(
+            diagnostics,
+            vec![Diagnostic {
+                line: 2,
+                text: "[1519-11-01 00:00] Guard #2 begins shift".to_string(),
+                issue: Issue::OverlappingShift,
+            }]
+        );
+        assert!(guard.timeline().contains("11-01  #1 "));
+    }
+
+    #[test]
+    fn replay_skips_entries_which_dont_fit() {
+        let err = replay(MESSY, Hours::Midnight).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "line 9: unknown action `[1518-03-02 00:45] dances`"
+        );
+
+        let log = MESSY.replace("dances", "wakes up");
+        let guard = replay(&log, Hours::Midnight).unwrap();
+        assert_eq!(guard.report(7).unwrap().total, 20);
+        assert_eq!(guard.report(8).unwrap().total, 0);
+        assert!(replay_checked(&log, Hours::Midnight).is_err());
+        assert_eq!(Day4.solve(&log).unwrap(), Output::new(7 * 10, 7 * 10));
+    }
+
+    #[test]
+    fn lenient_closes_sleep_at_shift_change() {
+        let (guard, diagnostics) = validate(MESSY, Hours::Midnight, Mode::Lenient);
+        assert_eq!(diagnostics.len(), 7);
+        assert_eq!(guard.report(7).unwrap().total, 20);
+        assert_eq!(guard.report(8).unwrap().total, 0);
+    }
+
+    /// `lines` in a random order
+    fn shuffled<'a>(lines: &[&'a str], seed: u64) -> Vec<&'a str> {
+        let mut rng = Rng::new(seed);
+        let mut lines = lines.to_vec();
+        for idx in (1..lines.len()).rev() {
+            lines.swap(idx, rng.below(idx + 1));
+        }
+        lines
+    }
+
+    #[test]
+    fn stream_matches_replay() {
+        for seed in 0..20 {
+            let (log, _) = random_log(seed);
+            let lines: Vec<&str> = log.lines().collect();
+            let mut stream = LogStream::new(Hours::All, Mode::Strict);
+
+            for (idx, line) in shuffled(&lines, seed).into_iter().enumerate() {
+                stream.push(line).unwrap();
+                if idx % 7 == 0 {
+                    let pushed = shuffled(&lines, seed)[..=idx].join("\n");
+                    let (guard, _) = validate(&pushed, Hours::All, Mode::Strict);
+                    assert_eq!(stream.reports(), guard.reports(), "{}", pushed);
+                }
+            }
+            let guard = replay(&log, Hours::All).unwrap();
+            assert_eq!(stream.reports(), guard.reports());
+            assert_eq!(stream.most_minutes().ok(), guard.most_minutes().ok());
+            assert_eq!(stream.most_sleeps().ok(), guard.most_sleeps().ok());
+        }
+    }
+
+    #[test]
+    fn stream_lenient() {
+        let mut stream = LogStream::new(Hours::Midnight, Mode::Lenient);
+        for line in shuffled(
+            &MESSY
+                .lines()
+                .filter(|l| !l.contains("dances"))
+                .collect::<Vec<_>>(),
+            1,
+        ) {
+            stream.push(line).unwrap();
+        }
+        assert_eq!(stream.report(7).unwrap().total, 20);
+        assert_eq!(stream.report(8).unwrap().total, 0);
+        assert_eq!(
+            stream
+                .push("[1518-03-02 00:45] dances")
+                .unwrap_err()
+                .to_string(),
+            "line 10: unknown action `[1518-03-02 00:45] dances`"
+        );
+    }
+
+    #[test]
+    fn full_stream() {
+        let mut stream = LogStream::default();
+        stream.extend(&load_input(INPUT).unwrap()).unwrap();
+        assert_eq!(stream.most_minutes().unwrap(), 4716);
+        assert_eq!(stream.most_sleeps().unwrap(), 117061);
     }
 
     #[test]
     fn full() {
-        assert_eq!(run(), Output { a: 4716, b: 117061 });
+        assert_eq!(run().unwrap(), Output::new(4716, 117061));
     }
 }
@@ -1,31 +1,55 @@
-use super::{parse_input, Output};
+use super::{load_input, AocError, Output, Result, Solution};
 use std::collections::HashSet;
 
 const INPUT: &str = "day5.txt";
 
-pub fn run() -> Output<usize, usize> {
-    let raw = parse_input(INPUT);
-    let input = raw.trim();
-    Output {
-        a: part_a(input),
-        b: part_b(input),
+pub struct Day5;
+
+impl Solution for Day5 {
+    type Input = String;
+    type A = usize;
+    type B = usize;
+
+    fn parse(&self, raw: &str) -> Result<Self::Input> {
+        Ok(raw.trim().to_string())
+    }
+
+    fn part_a(&self, input: &Self::Input) -> Result<Self::A> {
+        Ok(part_a(input))
     }
+
+    fn part_b(&self, input: &Self::Input) -> Result<Self::B> {
+        part_b(input)
+    }
+}
+
+/// Solve both parts for the puzzle input `input`
+pub fn solve(input: &str) -> Result<Output<usize, usize>> {
+    Day5.solve(input)
+}
+
+pub fn run() -> Result<Output<usize, usize>> {
+    solve(&load_input(INPUT)?)
 }
 
 fn part_a(polymer: &str) -> usize {
     react_polymer(polymer)
 }
 
-fn part_b(polymer: &str) -> usize {
+fn part_b(polymer: &str) -> Result<usize> {
     let lookup: HashSet<char> = polymer.chars().collect();
 
-    lookup.iter().fold(usize::max_value(), |curr_shortest, ch| {
-        let candidate: String = polymer
-            .chars()
-            .filter(|&c| !c.eq_ignore_ascii_case(&ch))
-            .collect();
-        std::cmp::min(react_polymer(&candidate), curr_shortest)
-    })
+    lookup
+        .iter()
+        .map(|ch| {
+            let candidate: String = polymer
+                .chars()
+                .filter(|&c| !c.eq_ignore_ascii_case(ch))
+                .collect();
+            react_polymer(&candidate)
+        })
+        .min()
+        .ok_or_else(|| AocError::unsolvable("the polymer has no units"))
 }
 
 fn react_polymer(polymer: &str) -> usize {
@@ -57,11 +81,21 @@ mod tests {
 
     #[test]
     fn b() {
-        assert_eq!(part_b("dabAcCaCBAcCcaDA"), 4);
+        assert_eq!(part_b("dabAcCaCBAcCcaDA").unwrap(), 4);
+    }
+
+    #[test]
+    fn empty_polymer() {
+        assert!(matches!(solve(" \n"), Err(AocError::Unsolvable(_))));
+    }
+
+    #[test]
+    fn example() {
+        assert_eq!(solve("dabAcCaCBAcCcaDA\n").unwrap(), Output::new(10, 4));
     }
 
     #[test]
     fn full() {
-        assert_eq!(run(), Output { a: 10250, b: 6188 });
+        assert_eq!(run().unwrap(), Output::new(10250, 6188));
     }
 }
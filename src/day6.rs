@@ -1,4 +1,4 @@
-use super::{parse_input, Output};
+use super::{load_input, AocError, Output, Result, Solution};
 use regex::Regex;
 use std::collections::{HashMap, HashSet};
 
@@ -9,7 +9,7 @@ lazy_static! {
 }
 
 #[derive(Debug)]
-struct Infinite {
+pub struct Infinite {
     min_x: isize,
     max_x: isize,
     min_y: isize,
@@ -21,14 +21,14 @@ impl Default for Infinite {
         Self {
             max_x: 0,
             max_y: 0,
-            min_x: isize::max_value(),
-            min_y: isize::max_value(),
+            min_x: isize::MAX,
+            min_y: isize::MAX,
         }
     }
 }
 
 #[derive(Default, Debug, Copy, Clone, Eq, Hash, PartialEq)]
-struct Coordinate {
+pub struct Coordinate {
     pub x: isize,
     pub y: isize,
 }
@@ -43,32 +43,68 @@ impl Coordinate {
     }
 }
 
-pub fn run() -> Output<isize, isize> {
-    let raw = parse_input(INPUT);
+/// The known coordinates and the bounding box enclosing them
+#[derive(Debug)]
+pub struct Chart {
+    coords: HashSet<Coordinate>,
+    inf: Infinite,
+}
 
-    let mut coords = HashSet::new();
-    let mut inf = Infinite::default();
+pub struct Day6;
+
+impl Solution for Day6 {
+    type Input = Chart;
+    type A = isize;
+    type B = isize;
+
+    fn parse(&self, raw: &str) -> Result<Self::Input> {
+        let mut coords = HashSet::new();
+        let mut inf = Infinite::default();
+
+        for (idx, line) in raw.lines().enumerate() {
+            let parse_err = |reason| AocError::parse(idx + 1, line, reason);
+            let cap = RE
+                .captures(line)
+                .ok_or_else(|| parse_err("expected coordinate `x, y`"))?;
+            let x: isize = cap[1]
+                .parse()
+                .map_err(|_| parse_err("number out of range"))?;
+            let y: isize = cap[2]
+                .parse()
+                .map_err(|_| parse_err("number out of range"))?;
+            let cord = Coordinate { x, y };
+            coords.insert(cord);
+
+            inf.max_x = std::cmp::max(x, inf.max_x);
+            inf.max_y = std::cmp::max(y, inf.max_y);
+            debug_assert!(x >= 0 && y >= 0, "no negative points");
+            inf.min_x = std::cmp::min(x, inf.min_x);
+            inf.min_y = std::cmp::min(y, inf.min_y);
+        }
 
-    for cap in RE.captures_iter(&raw) {
-        let x: isize = cap[1].parse().expect("well formed input");
-        let y: isize = cap[2].parse().expect("well formed input");
-        let cord = Coordinate { x, y };
-        coords.insert(cord);
+        Ok(Chart { coords, inf })
+    }
 
-        inf.max_x = std::cmp::max(x, inf.max_x);
-        inf.max_y = std::cmp::max(y, inf.max_y);
-        debug_assert!(x >= 0 && y >= 0, "no negative points");
-        inf.min_x = std::cmp::min(x, inf.min_x);
-        inf.min_y = std::cmp::min(y, inf.min_y);
+    fn part_a(&self, input: &Self::Input) -> Result<Self::A> {
+        part_a(input)
     }
 
-    Output {
-        a: part_a(&coords, &inf),
-        b: part_b(&coords, &inf),
+    fn part_b(&self, input: &Self::Input) -> Result<Self::B> {
+        Ok(part_b(input))
     }
 }
 
-fn part_a(coords: &HashSet<Coordinate>, inf: &Infinite) -> isize {
+/// Solve both parts for the puzzle input `input`
+pub fn solve(input: &str) -> Result<Output<isize, isize>> {
+    Day6.solve(input)
+}
+
+pub fn run() -> Result<Output<isize, isize>> {
+    solve(&load_input(INPUT)?)
+}
+
+fn part_a(chart: &Chart) -> Result<isize> {
+    let Chart { coords, inf } = chart;
     let mut points = HashMap::new();
 
     for x in inf.min_x..=inf.max_x {
@@ -79,7 +115,7 @@ fn part_a(coords: &HashSet<Coordinate>, inf: &Infinite) -> isize {
                 continue;
             }
 
-            let mut shortest_dist = isize::max_value();
+            let mut shortest_dist = isize::MAX;
             let mut uniq_cord = None;
 
             for known_cord in coords.iter() {
@@ -97,10 +133,15 @@ fn part_a(coords: &HashSet<Coordinate>, inf: &Infinite) -> isize {
             }
         }
     }
-    *points.values().max().unwrap()
+    points
+        .values()
+        .max()
+        .cloned()
+        .ok_or_else(|| AocError::unsolvable("every area is infinite"))
 }
 
-fn part_b(coords: &HashSet<Coordinate>, inf: &Infinite) -> isize {
+fn part_b(chart: &Chart) -> isize {
+    let Chart { coords, inf } = chart;
     const MAX_DISTANCE: isize = 9_999;
     let mut num_points = 0;
 
@@ -130,6 +171,6 @@ mod tests {
     use super::*;
     #[test]
     fn full() {
-        assert_eq!(run(), Output { a: 5333, b: 35334 });
+        assert_eq!(run().unwrap(), Output::new(5333, 35334));
     }
 }
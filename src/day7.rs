@@ -1,6 +1,12 @@
-use super::{parse_input, Output};
+use super::{load_input, AocError, Output, Result, Solution};
+use regex::Regex;
 use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 
+lazy_static! {
+    static ref RE: Regex =
+        Regex::new(r"^Step ([A-Z]) must be finished before step ([A-Z]) can begin\.$").unwrap();
+}
+
 const INPUT: &str = "day7.txt";
 
 #[derive(Clone, Debug, PartialEq)]
@@ -9,7 +15,7 @@ enum WorkerStatus {
     Busy(char, usize),
 }
 
-type DependencyGraph = HashMap<char, HashSet<char>>;
+pub type DependencyGraph = HashMap<char, HashSet<char>>;
 
 #[derive(Debug)]
 struct WorkerPool(Vec<WorkerStatus>);
@@ -45,6 +51,10 @@ impl WorkerPool {
         false
     }
 
+    fn is_idle(&self) -> bool {
+        self.available_spots() == self.0.len()
+    }
+
     fn available_spots(&self) -> usize {
         self.0.iter().fold(0, |acc, state| {
             if let WorkerStatus::Idle = state {
@@ -56,26 +66,63 @@ impl WorkerPool {
     }
 }
 
-fn from_str(steps: &str) -> DependencyGraph {
-    let mut dep = DependencyGraph::new();
-    for step in steps.lines().map(|l| l.trim()) {
-        let s = step.chars().nth(5).expect("well-formed input; qed");
-        let finish_before = step.chars().nth(36).expect("well-formed input; qed");
-        dep.entry(s).or_default().insert(finish_before);
-        dep.entry(finish_before).or_default();
+pub struct Day7;
+
+impl Solution for Day7 {
+    type Input = DependencyGraph;
+    type A = String;
+    type B = usize;
+
+    fn parse(&self, steps: &str) -> Result<Self::Input> {
+        let mut dep = DependencyGraph::new();
+        for (idx, step) in steps.lines().map(|l| l.trim()).enumerate() {
+            let caps = RE.captures(step).ok_or_else(|| {
+                AocError::parse(
+                    idx + 1,
+                    step,
+                    "expected `Step X must be finished before step Y can begin.`",
+                )
+            })?;
+            let step_of = |group: usize| {
+                caps[group]
+                    .chars()
+                    .next()
+                    .expect("the group is a single letter; qed")
+            };
+            let (s, finish_before) = (step_of(1), step_of(2));
+            if s == finish_before {
+                return Err(AocError::parse(idx + 1, step, "step depends on itself"));
+            }
+            dep.entry(s).or_default().insert(finish_before);
+            dep.entry(finish_before).or_default();
+        }
+        Ok(dep)
     }
-    dep
-}
 
-pub fn run() -> Output<String, usize> {
-    let steps = from_str(parse_input(INPUT).as_str());
-    Output {
-        a: part_a(steps.clone()),
-        b: part_b(steps),
+    fn part_a(&self, input: &Self::Input) -> Result<Self::A> {
+        part_a(input)
     }
+
+    fn part_b(&self, input: &Self::Input) -> Result<Self::B> {
+        part_b(input)
+    }
+}
+
+/// Solve both parts for the puzzle input `input`
+pub fn solve(input: &str) -> Result<Output<String, usize>> {
+    Day7.solve(input)
+}
+
+pub fn run() -> Result<Output<String, usize>> {
+    solve(&load_input(INPUT)?)
+}
+
+fn cycle() -> AocError {
+    AocError::unsolvable("the steps depend on each other in a cycle")
 }
 
-fn part_a(mut queue: DependencyGraph) -> String {
+fn part_a(steps: &DependencyGraph) -> Result<String> {
+    let mut queue = steps.clone();
     let mut finished = String::with_capacity(queue.keys().len());
 
     while !queue.is_empty() {
@@ -84,28 +131,22 @@ fn part_a(mut queue: DependencyGraph) -> String {
             if queue
                 .iter()
                 .filter(|(c2, _dep)| c1 != *c2)
-                .all(|(_, dep)| !dep.contains(&c1))
+                .all(|(_, dep)| !dep.contains(c1))
             {
                 candidates.insert(*c1);
             }
         }
 
-        let step = match candidates.iter().nth(0) {
-            Some(ref step) => **step,
-            None => *queue
-                .keys()
-                .nth(0)
-                .expect("queue has at least one element; qed"),
-        };
-
+        let step = *candidates.iter().next().ok_or_else(cycle)?;
         queue.remove(&step);
         finished.push(step);
     }
 
-    finished
+    Ok(finished)
 }
 
-fn part_b(mut queue: DependencyGraph) -> usize {
+fn part_b(steps: &DependencyGraph) -> Result<usize> {
+    let mut queue = steps.clone();
     let mut pool = WorkerPool::new(5);
     let mut pending: HashMap<char, HashSet<char>> = HashMap::new();
     let mut secs = 0;
@@ -120,13 +161,16 @@ fn part_b(mut queue: DependencyGraph) -> usize {
         if available_spots > 0 {
             let mut candidates: BTreeMap<char, HashSet<char>> = BTreeMap::new();
             for (curr_step, curr_dep) in &queue {
-                if pending.contains_key(&curr_step) || pending.values().any(|dep| dep.contains(&curr_step)) {
+                if pending.contains_key(curr_step)
+                    || pending.values().any(|dep| dep.contains(curr_step))
+                {
                     continue;
                 }
                 if queue
                     .iter()
                     .filter(|(&next_step, _)| *curr_step != next_step)
-                    .all(|(_, dep)| !dep.contains(&curr_step)) {
+                    .all(|(_, dep)| !dep.contains(curr_step))
+                {
                     candidates.insert(*curr_step, curr_dep.clone());
                 }
             }
@@ -138,24 +182,71 @@ fn part_b(mut queue: DependencyGraph) -> usize {
         }
 
         if !queue.is_empty() {
+            if pool.is_idle() {
+                return Err(cycle());
+            }
             secs += 1;
         }
     }
-    secs
+    Ok(secs)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn example() {
+        let steps = "Step C must be finished before step A can begin.
+Step C must be finished before step F can begin.
+Step A must be finished before step B can begin.
+Step A must be finished before step D can begin.
+Step B must be finished before step E can begin.
+Step D must be finished before step E can begin.
+Step F must be finished before step E can begin.";
+        let graph = Day7.parse(steps).unwrap();
+        assert_eq!(part_a(&graph).unwrap(), "CABDFE");
+    }
+
+    #[test]
+    fn cycle_is_unsolvable() {
+        let steps = "Step A must be finished before step B can begin.
+Step B must be finished before step A can begin.
+Step C must be finished before step A can begin.";
+        let graph = Day7.parse(steps).unwrap();
+        assert!(matches!(part_a(&graph), Err(AocError::Unsolvable(_))));
+        assert!(matches!(part_b(&graph), Err(AocError::Unsolvable(_))));
+    }
+
+    #[test]
+    fn malformed_steps() {
+        for (line, reason) in &[
+            (
+                "this line is junk but long enough to pass the check",
+                "expected `Step X must be finished before step Y can begin.`",
+            ),
+            (
+                "Step 1 must be finished before step A can begin.",
+                "expected `Step X must be finished before step Y can begin.`",
+            ),
+            (
+                "Step A must be finished before step A can begin.",
+                "step depends on itself",
+            ),
+        ] {
+            let raw = format!("Step A must be finished before step B can begin.\n{}", line);
+            assert_eq!(
+                Day7.parse(&raw).unwrap_err().to_string(),
+                AocError::parse(2, line, reason).to_string()
+            );
+        }
+    }
+
     #[test]
     fn full() {
         assert_eq!(
-            run(),
-            Output {
-                a: "GLMVWXZDKOUCEJRHFAPITSBQNY".to_string(),
-                b: 1105,
-            }
+            run().unwrap(),
+            Output::new("GLMVWXZDKOUCEJRHFAPITSBQNY".to_string(), 1105)
         );
     }
 }
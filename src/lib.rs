@@ -3,6 +3,7 @@ extern crate itertools;
 extern crate lazy_static;
 extern crate regex;
 
+pub mod answers;
 pub mod day1;
 #[allow(unused)]
 pub mod day10;
@@ -44,20 +45,478 @@ pub mod day7;
 pub mod day8;
 #[allow(unused)]
 pub mod day9;
+pub mod parallel;
+pub mod report;
 
+use std::any::Any;
+use std::env;
+use std::error::Error;
+use std::ffi::OsString;
+use std::fmt::{self, Display};
 use std::fs::File;
-use std::io::{BufReader, Read};
+use std::io::{self, BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 
+/// Environment variable pointing to the directory containing the `dayN.txt` inputs
+pub const INPUT_DIR_VAR: &str = "AOC_INPUT_DIR";
+
+/// The answers of a day's puzzle, `b` is `None` if only the first part is solved
 #[derive(Debug, PartialEq)]
 pub struct Output<A, B> {
     a: A,
-    b: B,
+    b: Option<B>,
+}
+
+impl<A, B> Output<A, B> {
+    pub fn new(a: A, b: B) -> Self {
+        Self { a, b: Some(b) }
+    }
+
+    /// Output of a day where only the first part is solved
+    pub fn partial(a: A) -> Self {
+        Self { a, b: None }
+    }
+
+    pub fn a(&self) -> &A {
+        &self.a
+    }
+
+    pub fn b(&self) -> Option<&B> {
+        self.b.as_ref()
+    }
+
+    pub fn into_parts(self) -> (A, Option<B>) {
+        (self.a, self.b)
+    }
+}
+
+impl<A: Display, B: Display> Output<A, B> {
+    /// Render as a JSON object, answers that are integers are rendered as numbers
+    /// and an unsolved part is rendered as `null`
+    pub fn to_json(&self) -> String {
+        let b = self
+            .b
+            .as_ref()
+            .map_or_else(|| "null".to_string(), |b| json_value(&b.to_string()));
+        format!(
+            "{{\"part1\":{},\"part2\":{}}}",
+            json_value(&self.a.to_string()),
+            b
+        )
+    }
+
+    /// Render as a tab-separated line, an unsolved part is rendered as an empty field
+    pub fn to_tsv(&self) -> String {
+        let b = self
+            .b
+            .as_ref()
+            .map_or_else(String::new, |b| tsv_field(&b.to_string()));
+        format!("{}\t{}", tsv_field(&self.a.to_string()), b)
+    }
+}
+
+impl<A: Display, B: Display> Display for Output<A, B> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Part 1: {} / Part 2: ", self.a)?;
+        match &self.b {
+            Some(b) => write!(f, "{}", b),
+            None => write!(f, "unsolved"),
+        }
+    }
+}
+
+fn json_value(answer: &str) -> String {
+    // only canonical integers are valid JSON numbers, e.g. not `+1` or `007`
+    if answer.parse::<i64>().ok().map(|n| n.to_string()).as_deref() == Some(answer) {
+        return answer.to_string();
+    }
+
+    let mut s = String::with_capacity(answer.len() + 2);
+    s.push('"');
+    for ch in answer.chars() {
+        match ch {
+            '"' => s.push_str("\\\""),
+            '\\' => s.push_str("\\\\"),
+            '\n' => s.push_str("\\n"),
+            '\r' => s.push_str("\\r"),
+            '\t' => s.push_str("\\t"),
+            ch if ch.is_control() => s.push_str(&format!("\\u{:04x}", ch as u32)),
+            ch => s.push(ch),
+        }
+    }
+    s.push('"');
+    s
+}
+
+fn tsv_field(answer: &str) -> String {
+    answer
+        .replace('\\', "\\\\")
+        .replace('\t', "\\t")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+}
+
+#[derive(Debug)]
+pub enum AocError {
+    /// The input file does not exist
+    MissingInput(PathBuf),
+    Io(io::Error),
+    /// A line of the input is malformed, `line` starts at 1
+    Parse {
+        line: usize,
+        text: String,
+        reason: String,
+    },
+    /// The input is well-formed but has no answer
+    Unsolvable(String),
+}
+
+impl AocError {
+    pub fn parse(line: usize, text: &str, reason: impl Display) -> Self {
+        AocError::Parse {
+            line,
+            text: text.to_string(),
+            reason: reason.to_string(),
+        }
+    }
+
+    pub fn unsolvable(reason: impl Display) -> Self {
+        AocError::Unsolvable(reason.to_string())
+    }
+}
+
+impl Display for AocError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AocError::MissingInput(path) => write!(f, "missing input file `{}`", path.display()),
+            AocError::Io(err) => write!(f, "I/O error: {}", err),
+            AocError::Parse { line, text, reason } => {
+                write!(f, "line {}: {} `{}`", line, reason, text)
+            }
+            AocError::Unsolvable(reason) => write!(f, "no solution: {}", reason),
+        }
+    }
+}
+
+impl Error for AocError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            AocError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for AocError {
+    fn from(err: io::Error) -> Self {
+        AocError::Io(err)
+    }
+}
+
+pub type Result<T> = std::result::Result<T, AocError>;
+
+/// A day's puzzle split into parsing the input and solving the two parts
+pub trait Solution {
+    type Input;
+    type A: Display;
+    type B: Display;
+
+    fn parse(&self, raw: &str) -> Result<Self::Input>;
+    fn part_a(&self, input: &Self::Input) -> Result<Self::A>;
+    fn part_b(&self, input: &Self::Input) -> Result<Self::B>;
+
+    /// Parse `raw` and solve both parts
+    fn solve(&self, raw: &str) -> Result<Output<Self::A, Self::B>> {
+        let input = self.parse(raw)?;
+        Ok(Output::new(self.part_a(&input)?, self.part_b(&input)?))
+    }
+}
+
+/// Type-erased `Solution` which makes it possible to store different days in the same registry
+pub trait Puzzle: Sync {
+    fn dyn_parse(&self, raw: &str) -> Result<Box<dyn Any>>;
+    fn dyn_part_a(&self, input: &dyn Any) -> Result<String>;
+    fn dyn_part_b(&self, input: &dyn Any) -> Result<String>;
+}
+
+impl<S> Puzzle for S
+where
+    S: Solution + Sync,
+    S::Input: 'static,
+{
+    fn dyn_parse(&self, raw: &str) -> Result<Box<dyn Any>> {
+        let input = self.parse(raw)?;
+        Ok(Box::new(input))
+    }
+
+    fn dyn_part_a(&self, input: &dyn Any) -> Result<String> {
+        let input = input
+            .downcast_ref()
+            .expect("input is parsed by the same solution; qed");
+        self.part_a(input).map(|a| a.to_string())
+    }
+
+    fn dyn_part_b(&self, input: &dyn Any) -> Result<String> {
+        let input = input
+            .downcast_ref()
+            .expect("input is parsed by the same solution; qed");
+        self.part_b(input).map(|b| b.to_string())
+    }
+}
+
+/// An entry in the calendar, `solution` is `None` for days that are not yet solved
+pub struct Day {
+    pub number: usize,
+    pub solution: Option<&'static dyn Puzzle>,
 }
 
-pub fn parse_input(input: &str) -> String {
-    let file = File::open(input).expect("File open error");
+impl Day {
+    const fn solved(number: usize, solution: &'static dyn Puzzle) -> Self {
+        Self {
+            number,
+            solution: Some(solution),
+        }
+    }
+
+    const fn unsolved(number: usize) -> Self {
+        Self {
+            number,
+            solution: None,
+        }
+    }
+
+    /// Name of the day's puzzle input
+    pub fn input(&self) -> String {
+        format!("day{}.txt", self.number)
+    }
+
+    /// Location of the day's puzzle input, see `input_path`
+    pub fn input_path(&self) -> PathBuf {
+        input_path(&self.input())
+    }
+
+    /// Read the day's puzzle input, see `load_input`
+    pub fn load_input(&self) -> Result<String> {
+        load_input(&self.input())
+    }
+}
+
+/// All days of the calendar ordered by day number
+pub static CALENDAR: [Day; 25] = [
+    Day::solved(1, &day1::Day1),
+    Day::solved(2, &day2::Day2),
+    Day::solved(3, &day3::Day3),
+    Day::solved(4, &day4::Day4),
+    Day::solved(5, &day5::Day5),
+    Day::solved(6, &day6::Day6),
+    Day::solved(7, &day7::Day7),
+    Day::unsolved(8),
+    Day::unsolved(9),
+    Day::unsolved(10),
+    Day::unsolved(11),
+    Day::unsolved(12),
+    Day::unsolved(13),
+    Day::unsolved(14),
+    Day::unsolved(15),
+    Day::unsolved(16),
+    Day::unsolved(17),
+    Day::unsolved(18),
+    Day::unsolved(19),
+    Day::unsolved(20),
+    Day::unsolved(21),
+    Day::unsolved(22),
+    Day::unsolved(23),
+    Day::unsolved(24),
+    Day::unsolved(25),
+];
+
+/// Look up `day` in the calendar
+pub fn day(number: usize) -> Option<&'static Day> {
+    number.checked_sub(1).and_then(|idx| CALENDAR.get(idx))
+}
+
+pub fn parse_input(input: impl AsRef<Path>) -> Result<String> {
+    let input = input.as_ref();
+    let file = File::open(input).map_err(|err| match err.kind() {
+        io::ErrorKind::NotFound => AocError::MissingInput(input.to_path_buf()),
+        _ => AocError::Io(err),
+    })?;
     let mut buf_reader = BufReader::new(file);
     let mut s = String::new();
-    buf_reader.read_to_string(&mut s).expect("File read error");
-    s
+    buf_reader.read_to_string(&mut s)?;
+    Ok(s)
+}
+
+/// Resolve the input `name` against `$AOC_INPUT_DIR`, or the working directory if it is not set
+pub fn input_path(name: &str) -> PathBuf {
+    resolve_input(env::var_os(INPUT_DIR_VAR), name)
+}
+
+fn resolve_input(dir: Option<OsString>, name: &str) -> PathBuf {
+    match dir {
+        Some(dir) => PathBuf::from(dir).join(name),
+        None => PathBuf::from(name),
+    }
+}
+
+/// Read the input `name` from the input directory, see `input_path`
+///
+/// With the `embed-inputs` feature the inputs compiled into the crate are used instead,
+/// unless `$AOC_INPUT_DIR` is set
+pub fn load_input(name: &str) -> Result<String> {
+    load_from(env::var_os(INPUT_DIR_VAR), name)
+}
+
+fn load_from(dir: Option<OsString>, name: &str) -> Result<String> {
+    match embedded_input(name) {
+        Some(input) if dir.is_none() => Ok(input.to_string()),
+        _ => parse_input(resolve_input(dir, name)),
+    }
+}
+
+#[cfg(feature = "embed-inputs")]
+fn embedded_input(name: &str) -> Option<&'static str> {
+    match name {
+        "day1.txt" => Some(include_str!("../day1.txt")),
+        "day2.txt" => Some(include_str!("../day2.txt")),
+        "day3.txt" => Some(include_str!("../day3.txt")),
+        "day4.txt" => Some(include_str!("../day4.txt")),
+        "day5.txt" => Some(include_str!("../day5.txt")),
+        "day6.txt" => Some(include_str!("../day6.txt")),
+        "day7.txt" => Some(include_str!("../day7.txt")),
+        _ => None,
+    }
+}
+
+#[cfg(not(feature = "embed-inputs"))]
+fn embedded_input(_name: &str) -> Option<&'static str> {
+    None
+}
+
+/// Read the input from `path` where `-` means stdin
+pub fn read_input(path: impl AsRef<Path>) -> Result<String> {
+    let path = path.as_ref();
+    if path == Path::new("-") {
+        let mut s = String::new();
+        io::stdin().read_to_string(&mut s)?;
+        Ok(s)
+    } else {
+        parse_input(path)
+    }
+}
+
+/// Parse every line of `raw`, errors are tagged with the offending line
+pub fn parse_lines<T>(raw: &str) -> Result<Vec<T>>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    raw.lines()
+        .enumerate()
+        .map(|(idx, line)| {
+            line.parse()
+                .map_err(|err| AocError::parse(idx + 1, line, err))
+        })
+        .collect()
+}
+
+/// Deterministic pseudo-random numbers for the tests, a 64-bit linear congruential generator
+#[cfg(test)]
+pub(crate) struct Rng(u64);
+
+#[cfg(test)]
+impl Rng {
+    pub(crate) fn new(seed: u64) -> Self {
+        Rng(seed)
+    }
+
+    /// The next number in `0..range`
+    pub(crate) fn below(&mut self, range: usize) -> usize {
+        self.0 = self
+            .0
+            .wrapping_mul(6_364_136_223_846_793_005)
+            .wrapping_add(1);
+        ((self.0 >> 33) % range as u64) as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn calendar() {
+        for (idx, entry) in CALENDAR.iter().enumerate() {
+            assert_eq!(entry.number, idx + 1);
+        }
+        assert_eq!(day(4).map(Day::input), Some("day4.txt".to_string()));
+        assert!(day(8).unwrap().solution.is_none());
+        assert!(day(0).is_none());
+        assert!(day(26).is_none());
+    }
+
+    #[test]
+    fn input_dir() {
+        assert_eq!(resolve_input(None, "day1.txt"), PathBuf::from("day1.txt"));
+        assert_eq!(
+            resolve_input(Some("/inputs/alice".into()), "day1.txt"),
+            PathBuf::from("/inputs/alice/day1.txt")
+        );
+    }
+
+    #[cfg(feature = "embed-inputs")]
+    #[test]
+    fn embedded() {
+        assert!(embedded_input("day1.txt").is_some());
+        assert_eq!(embedded_input("day8.txt"), None);
+        assert_eq!(
+            load_from(None, "day1.txt").unwrap(),
+            embedded_input("day1.txt").unwrap()
+        );
+
+        let dir = env::temp_dir().join(format!("aoc-embedded-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("day1.txt"), "+5\n").unwrap();
+        let from_disk = load_from(Some(dir.clone().into()), "day1.txt");
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(from_disk.unwrap(), "+5\n");
+    }
+
+    #[test]
+    fn missing_input() {
+        match parse_input("no-such-day.txt") {
+            Err(AocError::MissingInput(path)) => assert_eq!(path, PathBuf::from("no-such-day.txt")),
+            other => panic!("expected missing input, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parse_error_line() {
+        let err = parse_lines::<isize>("+1\n-2\nthree\n+4").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "line 3: invalid digit found in string `three`"
+        );
+    }
+
+    #[test]
+    fn output() {
+        let output = Output::new(497, "mxhw".to_string());
+        assert_eq!(output.a(), &497);
+        assert_eq!(output.b(), Some(&"mxhw".to_string()));
+        assert_eq!(output.to_string(), "Part 1: 497 / Part 2: mxhw");
+        assert_eq!(output.to_json(), r#"{"part1":497,"part2":"mxhw"}"#);
+        assert_eq!(output.to_tsv(), "497\tmxhw");
+
+        let partial: Output<_, usize> = Output::partial("a\t\"b\"".to_string());
+        assert_eq!(partial.b(), None);
+        assert_eq!(partial.to_string(), "Part 1: a\t\"b\" / Part 2: unsolved");
+        assert_eq!(partial.to_json(), r#"{"part1":"a\t\"b\"","part2":null}"#);
+        assert_eq!(partial.to_tsv(), "a\\t\"b\"\t");
+        assert_eq!(
+            Output::new("+1", "007").to_json(),
+            r#"{"part1":"+1","part2":"007"}"#
+        );
+    }
 }
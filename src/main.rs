@@ -0,0 +1,439 @@
+extern crate aoc_2018;
+
+use aoc_2018::answers::{self, Answers, Part, Verdict, ANSWERS_FILE};
+use aoc_2018::parallel::{self, Job, Options, Outcome, Status};
+use aoc_2018::report::{self, Report};
+use aoc_2018::*;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::time::{Duration, Instant};
+
+const USAGE: &str = "Usage:
+    aoc <day> [a|b|both] [input]            solve a day
+    aoc all [a|b|both]                      solve every solved day
+    aoc parallel [--threads N] [--timeout SECS] [--budget SECS]
+                                            solve every solved day concurrently
+    aoc list                                list which days are solved
+    aoc verify                              check every solved day against the known answers
+    aoc record <day> <a|b> <answer> [input] store a known answer
+    aoc report [md|csv] [runs]              time every solved day, 10 runs by default
+    aoc readme [runs]                       update the timing table in README.md
+
+The input defaults to `dayN.txt` in `$AOC_INPUT_DIR` or the working directory, `-` reads stdin";
+
+const README: &str = "README.md";
+const DEFAULT_RUNS: usize = 10;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Selection {
+    A,
+    B,
+    Both,
+}
+
+impl Selection {
+    fn parts(self) -> &'static [Part] {
+        match self {
+            Selection::A => &[Part::A],
+            Selection::B => &[Part::B],
+            Selection::Both => &[Part::A, Part::B],
+        }
+    }
+}
+
+/// Answer of one part together with the time it took to compute
+struct Timed {
+    part: Part,
+    answer: String,
+    elapsed: Duration,
+}
+
+fn timed<T>(f: impl FnOnce() -> T) -> (T, Duration) {
+    let now = Instant::now();
+    let res = f();
+    (res, now.elapsed())
+}
+
+/// Solve the selected parts of `solution` for the input `raw`
+fn solve(solution: &dyn Puzzle, raw: &str, selection: Selection) -> Result<Vec<Timed>> {
+    let input = solution.dyn_parse(raw)?;
+    let mut solved = Vec::new();
+
+    for &part in selection.parts() {
+        let (answer, elapsed) = timed(|| match part {
+            Part::A => solution.dyn_part_a(&*input),
+            Part::B => solution.dyn_part_b(&*input),
+        });
+        solved.push(Timed {
+            part,
+            answer: answer?,
+            elapsed,
+        });
+    }
+    Ok(solved)
+}
+
+/// Read the input at `path`, or the day's puzzle input if there is none
+fn day_input(day: &Day, path: Option<&Path>) -> Result<String> {
+    match path {
+        Some(path) => read_input(path),
+        None => day.load_input(),
+    }
+}
+
+/// Solve and print `day`, returns `Ok(false)` if it has not been solved yet
+fn print_day(day: &Day, selection: Selection, path: Option<&Path>) -> Result<bool> {
+    let solution = match day.solution {
+        Some(solution) => solution,
+        None => return Ok(false),
+    };
+
+    for t in solve(solution, &day_input(day, path)?, selection)? {
+        println!(
+            "day{} part {:?}: {} ({:?})",
+            day.number, t.part, t.answer, t.elapsed
+        );
+    }
+    Ok(true)
+}
+
+fn print_error(day: &Day, path: &Path, err: &AocError) {
+    eprintln!("day{} ({}): {}", day.number, path.display(), err);
+}
+
+fn list() {
+    for day in CALENDAR.iter() {
+        let status = if day.solution.is_some() {
+            "solved"
+        } else {
+            "not yet solved"
+        };
+        println!("day{}: {}", day.number, status);
+    }
+}
+
+fn all(selection: Selection) {
+    for day in CALENDAR.iter() {
+        match print_day(day, selection, None) {
+            Ok(true) => (),
+            Ok(false) => println!("day{}: not yet solved", day.number),
+            Err(err) => print_error(day, &day.input_path(), &err),
+        }
+    }
+}
+
+fn one(day: &Day, selection: Selection, path: Option<&String>) {
+    let input = path.map(Path::new);
+    let path = input.map(PathBuf::from).unwrap_or_else(|| day.input_path());
+    match print_day(day, selection, input) {
+        Ok(true) => (),
+        Ok(false) => {
+            eprintln!("day{}: not yet solved", day.number);
+            process::exit(1);
+        }
+        Err(err) => {
+            print_error(day, &path, &err);
+            process::exit(1);
+        }
+    }
+}
+
+/// Run every solved day and compare against the known answers, returns `false` on any failure
+fn verify(answers: &Answers) -> bool {
+    let (mut passed, mut failed, mut unknown) = (0, 0, 0);
+
+    for day in CALENDAR.iter() {
+        let solution = match day.solution {
+            Some(solution) => solution,
+            None => continue,
+        };
+        let path = day.input_path();
+        let solved = day.load_input().and_then(|raw| {
+            let solved = solve(solution, &raw, Selection::Both)?;
+            Ok((answers::digest(&raw), solved))
+        });
+
+        let (digest, solved) = match solved {
+            Ok(solved) => solved,
+            Err(err) => {
+                failed += 1;
+                println!("day{}: FAIL ({}): {}", day.number, path.display(), err);
+                continue;
+            }
+        };
+
+        for t in solved {
+            match answers.check(day.number, &digest, t.part, &t.answer) {
+                Verdict::Pass => {
+                    passed += 1;
+                    println!(
+                        "day{} part {:?}: pass ({:?})",
+                        day.number, t.part, t.elapsed
+                    );
+                }
+                Verdict::Fail { expected } => {
+                    failed += 1;
+                    println!(
+                        "day{} part {:?}: FAIL got {}, expected {} ({:?})",
+                        day.number, t.part, t.answer, expected, t.elapsed
+                    );
+                }
+                Verdict::Unknown => {
+                    unknown += 1;
+                    println!(
+                        "day{} part {:?}: unknown {} ({:?})",
+                        day.number, t.part, t.answer, t.elapsed
+                    );
+                }
+            }
+        }
+    }
+
+    println!("{} passed, {} failed, {} unknown", passed, failed, unknown);
+    failed == 0
+}
+
+/// Store `answer` for `day`, returns `Ok(false)` if it has not been solved yet
+fn record(day: &Day, part: Part, answer: &str, path: Option<&String>) -> Result<bool> {
+    if day.solution.is_none() {
+        return Ok(false);
+    }
+    let digest = answers::digest(&day_input(day, path.map(Path::new))?);
+    let mut answers = Answers::load(ANSWERS_FILE)?;
+
+    match answers.record(day.number, &digest, part, answer) {
+        Some(ref old) if old != answer => println!(
+            "day{} part {:?}: replaced {} with {}",
+            day.number, part, old, answer
+        ),
+        _ => println!("day{} part {:?}: recorded {}", day.number, part, answer),
+    }
+    answers.save(ANSWERS_FILE)?;
+    Ok(true)
+}
+
+fn status(outcome: &Outcome) -> String {
+    match &outcome.status {
+        Status::Solved { a, b } => format!("ok {} / {}", a, b),
+        Status::Failed(err) => format!("FAIL {}", err),
+        Status::Panicked => "PANIC".to_string(),
+        Status::TimedOut => "TIMEOUT".to_string(),
+        Status::Skipped => "skipped, out of budget".to_string(),
+    }
+}
+
+/// Solve all days concurrently, returns `false` if any day did not finish successfully
+fn run_parallel(options: &Options) -> bool {
+    let jobs = CALENDAR
+        .iter()
+        .filter_map(|day| {
+            day.solution.map(|solution| Job {
+                day: day.number,
+                solution,
+                input: None,
+            })
+        })
+        .collect();
+
+    let (outcomes, wall) = timed(|| {
+        parallel::run(jobs, options, |outcome| {
+            println!(
+                "day{}: {} ({:?})",
+                outcome.day,
+                status(outcome),
+                outcome.elapsed
+            )
+        })
+    });
+
+    println!();
+    println!("{:<5} {:<9} {:>12}", "day", "status", "time");
+    let mut ok = 0;
+    for outcome in &outcomes {
+        let label = match outcome.status {
+            Status::Solved { .. } => {
+                ok += 1;
+                "ok"
+            }
+            Status::Failed(_) => "failed",
+            Status::Panicked => "panicked",
+            Status::TimedOut => "timeout",
+            Status::Skipped => "skipped",
+        };
+        println!(
+            "{:<5} {:<9} {:>12}",
+            outcome.day,
+            label,
+            format!("{:.2?}", outcome.elapsed)
+        );
+    }
+    let cpu: Duration = outcomes.iter().map(|outcome| outcome.elapsed).sum();
+    println!(
+        "{}/{} days solved, {:.2?} wall clock, {:.2?} summed",
+        ok,
+        outcomes.len(),
+        wall,
+        cpu
+    );
+    ok == outcomes.len()
+}
+
+fn parse_options(args: &[String]) -> Option<Options> {
+    let mut options = Options::default();
+    let mut args = args.iter();
+
+    while let Some(flag) = args.next() {
+        let value: u64 = args.next()?.parse().ok()?;
+        match flag.as_str() {
+            "--threads" if value > 0 => options.threads = value as usize,
+            "--timeout" => options.timeout = Duration::from_secs(value),
+            "--budget" => options.budget = Some(Duration::from_secs(value)),
+            _ => return None,
+        }
+    }
+    Some(options)
+}
+
+fn timings(runs: usize) -> Report {
+    let mut report = Report::default();
+
+    for day in CALENDAR.iter() {
+        let solution = match day.solution {
+            Some(solution) => solution,
+            None => continue,
+        };
+        let path = day.input_path();
+        let measured = day
+            .load_input()
+            .and_then(|raw| report.measure(day.number, solution, &raw, runs));
+        if let Err(err) = measured {
+            print_error(day, &path, &err);
+        }
+    }
+    report
+}
+
+/// Rewrite the timing table in the README, returns `Ok(false)` if it lacks the markers
+fn update_readme(runs: usize) -> Result<bool> {
+    let readme = parse_input(README)?;
+    let table = timings(runs).to_markdown();
+    match report::splice_readme(&readme, &table) {
+        Some(updated) => {
+            fs::write(README, updated)?;
+            Ok(true)
+        }
+        None => Ok(false),
+    }
+}
+
+fn parse_runs(arg: Option<&String>) -> Option<usize> {
+    match arg {
+        None => Some(DEFAULT_RUNS),
+        Some(runs) => runs.parse().ok().filter(|&runs| runs > 0),
+    }
+}
+
+fn parse_selection(arg: Option<&String>) -> Option<Selection> {
+    match arg.map(String::as_str) {
+        None | Some("both") => Some(Selection::Both),
+        Some(part) => match part.parse() {
+            Ok(Part::A) => Some(Selection::A),
+            Ok(Part::B) => Some(Selection::B),
+            Err(_) => None,
+        },
+    }
+}
+
+fn parse_day(arg: Option<&String>) -> Option<&'static Day> {
+    arg.and_then(|number| number.parse().ok()).and_then(day)
+}
+
+fn usage() -> ! {
+    eprintln!("{}", USAGE);
+    process::exit(2);
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    match args.first().map(String::as_str) {
+        Some("list") => list(),
+        Some("all") if args.len() <= 2 => {
+            all(parse_selection(args.get(1)).unwrap_or_else(|| usage()))
+        }
+        Some("parallel") => {
+            let options = parse_options(&args[1..]).unwrap_or_else(|| usage());
+            if !run_parallel(&options) {
+                process::exit(1);
+            }
+        }
+        Some("verify") if args.len() == 1 => {
+            let passed = match Answers::load(ANSWERS_FILE) {
+                Ok(answers) => verify(&answers),
+                Err(err) => {
+                    eprintln!("{}: {}", ANSWERS_FILE, err);
+                    false
+                }
+            };
+            if !passed {
+                process::exit(1);
+            }
+        }
+        Some("record") if args.len() == 4 || args.len() == 5 => {
+            let day = parse_day(args.get(1)).unwrap_or_else(|| usage());
+            let part = args[2].parse().unwrap_or_else(|_| usage());
+            match record(day, part, &args[3], args.get(4)) {
+                Ok(true) => (),
+                Ok(false) => {
+                    eprintln!("day{}: not yet solved", day.number);
+                    process::exit(1);
+                }
+                Err(err) => {
+                    eprintln!("day{}: {}", day.number, err);
+                    process::exit(1);
+                }
+            }
+        }
+        Some("report") if args.len() <= 3 => {
+            let csv = match args.get(1).map(String::as_str) {
+                None | Some("md") => false,
+                Some("csv") => true,
+                Some(_) => usage(),
+            };
+            let runs = parse_runs(args.get(2)).unwrap_or_else(|| usage());
+            let report = timings(runs);
+            if csv {
+                print!("{}", report.to_csv());
+            } else {
+                print!("{}", report.to_markdown());
+            }
+        }
+        Some("readme") if args.len() <= 2 => {
+            let runs = parse_runs(args.get(1)).unwrap_or_else(|| usage());
+            match update_readme(runs) {
+                Ok(true) => (),
+                Ok(false) => {
+                    eprintln!(
+                        "{}: missing `{}` and `{}`",
+                        README,
+                        report::README_START,
+                        report::README_END
+                    );
+                    process::exit(1);
+                }
+                Err(err) => {
+                    eprintln!("{}: {}", README, err);
+                    process::exit(1);
+                }
+            }
+        }
+        Some(_) if args.len() <= 3 => {
+            let day = parse_day(args.first()).unwrap_or_else(|| usage());
+            let selection = parse_selection(args.get(1)).unwrap_or_else(|| usage());
+            one(day, selection, args.get(2));
+        }
+        _ => usage(),
+    }
+}
@@ -0,0 +1,227 @@
+//! Run several days concurrently on a fixed number of worker threads
+//!
+//! A day that exceeds its timeout is reported as timed out and abandoned, the thread computing
+//! it can't be cancelled and keeps running in the background until the process exits.
+
+use super::{load_input, read_input, AocError, Puzzle, Result};
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+pub struct Job {
+    pub day: usize,
+    pub solution: &'static dyn Puzzle,
+    /// Input to read, `None` loads the day's puzzle input with `load_input`
+    pub input: Option<PathBuf>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Options {
+    pub threads: usize,
+    /// Maximum time a single day may take, including reading its input
+    pub timeout: Duration,
+    /// Days which have not started when the budget runs out are skipped
+    pub budget: Option<Duration>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            threads: 4,
+            timeout: Duration::from_secs(10),
+            budget: None,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum Status {
+    Solved { a: String, b: String },
+    Failed(AocError),
+    Panicked,
+    TimedOut,
+    Skipped,
+}
+
+#[derive(Debug)]
+pub struct Outcome {
+    pub day: usize,
+    pub status: Status,
+    pub elapsed: Duration,
+}
+
+/// Run all `jobs` and call `on_outcome` as soon as each day finishes,
+/// the returned outcomes are ordered by day
+pub fn run(
+    jobs: Vec<Job>,
+    options: &Options,
+    mut on_outcome: impl FnMut(&Outcome),
+) -> Vec<Outcome> {
+    let total = jobs.len();
+    let deadline = options.budget.map(|budget| Instant::now() + budget);
+    let queue = Arc::new(Mutex::new(jobs.into_iter().collect::<VecDeque<_>>()));
+    let (tx, rx) = mpsc::channel();
+
+    for _ in 0..options.threads.max(1).min(total) {
+        let queue = queue.clone();
+        let tx = tx.clone();
+        let timeout = options.timeout;
+        thread::spawn(move || loop {
+            let job = match queue.lock().expect("worker panicked").pop_front() {
+                Some(job) => job,
+                None => break,
+            };
+            if tx.send(execute(job, timeout, deadline)).is_err() {
+                break;
+            }
+        });
+    }
+    drop(tx);
+
+    let mut outcomes: Vec<Outcome> = rx
+        .iter()
+        .take(total)
+        .inspect(|outcome| on_outcome(outcome))
+        .collect();
+    outcomes.sort_by_key(|outcome| outcome.day);
+    outcomes
+}
+
+fn execute(job: Job, timeout: Duration, deadline: Option<Instant>) -> Outcome {
+    let started = Instant::now();
+    let timeout = match deadline {
+        Some(deadline) if deadline <= started => {
+            return Outcome {
+                day: job.day,
+                status: Status::Skipped,
+                elapsed: Duration::default(),
+            }
+        }
+        Some(deadline) => timeout.min(deadline - started),
+        None => timeout,
+    };
+
+    let (tx, rx) = mpsc::channel();
+    let Job {
+        day,
+        solution,
+        input,
+    } = job;
+    thread::spawn(move || {
+        let _ = tx.send(solve(day, solution, input.as_deref()));
+    });
+
+    let status = match rx.recv_timeout(timeout) {
+        Ok(Ok((a, b))) => Status::Solved { a, b },
+        Ok(Err(err)) => Status::Failed(err),
+        Err(RecvTimeoutError::Timeout) => Status::TimedOut,
+        Err(RecvTimeoutError::Disconnected) => Status::Panicked,
+    };
+    Outcome {
+        day,
+        status,
+        elapsed: started.elapsed(),
+    }
+}
+
+fn solve(day: usize, solution: &dyn Puzzle, input: Option<&Path>) -> Result<(String, String)> {
+    let raw = match input {
+        Some(path) => read_input(path)?,
+        None => load_input(&format!("day{}.txt", day))?,
+    };
+    let input = solution.dyn_parse(&raw)?;
+    Ok((solution.dyn_part_a(&*input)?, solution.dyn_part_b(&*input)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Solution;
+
+    struct Sleepy(Duration);
+
+    impl Solution for Sleepy {
+        type Input = ();
+        type A = usize;
+        type B = usize;
+
+        fn parse(&self, _raw: &str) -> Result<()> {
+            Ok(())
+        }
+
+        fn part_a(&self, _input: &()) -> Result<usize> {
+            thread::sleep(self.0);
+            Ok(1)
+        }
+
+        fn part_b(&self, _input: &()) -> Result<usize> {
+            Ok(2)
+        }
+    }
+
+    static FAST: Sleepy = Sleepy(Duration::from_millis(0));
+    static SLOW: Sleepy = Sleepy(Duration::from_secs(3600));
+
+    fn job(day: usize, solution: &'static dyn Puzzle) -> Job {
+        Job {
+            day,
+            solution,
+            input: Some(PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("Cargo.toml")),
+        }
+    }
+
+    #[test]
+    fn timeout_does_not_block_other_days() {
+        let options = Options {
+            threads: 2,
+            timeout: Duration::from_millis(100),
+            budget: None,
+        };
+        let mut streamed = 0;
+        let outcomes = run(
+            vec![job(2, &SLOW), job(1, &FAST), job(3, &FAST)],
+            &options,
+            |_| streamed += 1,
+        );
+
+        assert_eq!(streamed, 3);
+        assert_eq!(
+            outcomes.iter().map(|o| o.day).collect::<Vec<_>>(),
+            vec![1, 2, 3]
+        );
+        match &outcomes[0].status {
+            Status::Solved { a, b } => assert_eq!((a.as_str(), b.as_str()), ("1", "2")),
+            other => panic!("expected solved, got {:?}", other),
+        }
+        assert!(matches!(outcomes[1].status, Status::TimedOut));
+        assert!(matches!(outcomes[2].status, Status::Solved { .. }));
+    }
+
+    #[test]
+    fn budget_skips_remaining_days() {
+        let options = Options {
+            threads: 1,
+            timeout: Duration::from_secs(60),
+            budget: Some(Duration::from_millis(100)),
+        };
+        let outcomes = run(vec![job(1, &SLOW), job(2, &FAST)], &options, |_| ());
+
+        assert!(matches!(outcomes[0].status, Status::TimedOut));
+        assert!(matches!(outcomes[1].status, Status::Skipped));
+    }
+
+    #[test]
+    fn missing_input_fails() {
+        let mut missing = job(1, &FAST);
+        missing.input = Some(PathBuf::from("no-such-day.txt"));
+        let outcomes = run(vec![missing], &Options::default(), |_| ());
+
+        assert!(matches!(
+            outcomes[0].status,
+            Status::Failed(AocError::MissingInput(_))
+        ));
+    }
+}
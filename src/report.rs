@@ -0,0 +1,258 @@
+//! Timing reports over repeated runs of the solved days, rendered as Markdown or CSV
+
+use super::{Puzzle, Result};
+use std::fmt::{self, Display};
+use std::time::{Duration, Instant};
+
+/// Marks the start of the generated table in the README
+pub const README_START: &str = "<!-- timings:start -->";
+/// Marks the end of the generated table in the README
+pub const README_END: &str = "<!-- timings:end -->";
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Phase {
+    Parse,
+    PartA,
+    PartB,
+}
+
+impl Display for Phase {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Phase::Parse => write!(f, "parse"),
+            Phase::PartA => write!(f, "part A"),
+            Phase::PartB => write!(f, "part B"),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct Stats {
+    pub median: Duration,
+    pub min: Duration,
+    pub max: Duration,
+}
+
+impl Stats {
+    /// Summarize `samples`, returns `None` if there are no samples
+    pub fn from_samples(samples: &[Duration]) -> Option<Self> {
+        let mut sorted = samples.to_vec();
+        sorted.sort();
+        let len = sorted.len();
+        if len == 0 {
+            return None;
+        }
+        let median = if len.is_multiple_of(2) {
+            (sorted[len / 2 - 1] + sorted[len / 2]) / 2
+        } else {
+            sorted[len / 2]
+        };
+        Some(Self {
+            median,
+            min: sorted[0],
+            max: sorted[len - 1],
+        })
+    }
+
+    fn add(self, other: Stats) -> Stats {
+        Stats {
+            median: self.median + other.median,
+            min: self.min + other.min,
+            max: self.max + other.max,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Row {
+    pub day: usize,
+    pub phase: Phase,
+    pub stats: Stats,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Report {
+    rows: Vec<Row>,
+}
+
+impl Report {
+    pub fn push(&mut self, day: usize, phase: Phase, stats: Stats) {
+        self.rows.push(Row { day, phase, stats });
+    }
+
+    /// Run `solution` on `raw` `runs` times and add the timings of every phase of `day`
+    pub fn measure(
+        &mut self,
+        day: usize,
+        solution: &dyn Puzzle,
+        raw: &str,
+        runs: usize,
+    ) -> Result<()> {
+        let mut samples = [Vec::new(), Vec::new(), Vec::new()];
+
+        for _ in 0..runs {
+            let now = Instant::now();
+            let input = solution.dyn_parse(raw)?;
+            samples[0].push(now.elapsed());
+
+            let now = Instant::now();
+            solution.dyn_part_a(&*input)?;
+            samples[1].push(now.elapsed());
+
+            let now = Instant::now();
+            solution.dyn_part_b(&*input)?;
+            samples[2].push(now.elapsed());
+        }
+
+        for (&phase, samples) in [Phase::Parse, Phase::PartA, Phase::PartB]
+            .iter()
+            .zip(&samples)
+        {
+            if let Some(stats) = Stats::from_samples(samples) {
+                self.push(day, phase, stats);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn rows(&self) -> &[Row] {
+        &self.rows
+    }
+
+    /// Sum of the median, min and max over all days and phases
+    pub fn total(&self) -> Stats {
+        self.rows
+            .iter()
+            .fold(Stats::default(), |total, row| total.add(row.stats))
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let mut s = String::from("| Day | Phase | Median | Min | Max |\n");
+        s.push_str("|----:|:------|-------:|----:|----:|\n");
+        for row in &self.rows {
+            s.push_str(&format!(
+                "| {} | {} | {} | {} | {} |\n",
+                row.day,
+                row.phase,
+                human(row.stats.median),
+                human(row.stats.min),
+                human(row.stats.max)
+            ));
+        }
+        let total = self.total();
+        s.push_str(&format!(
+            "| **Total** | | **{}** | {} | {} |\n",
+            human(total.median),
+            human(total.min),
+            human(total.max)
+        ));
+        s
+    }
+
+    /// Render as CSV with durations in nanoseconds
+    pub fn to_csv(&self) -> String {
+        let mut s = String::from("day,phase,median_ns,min_ns,max_ns\n");
+        for row in &self.rows {
+            s.push_str(&format!(
+                "{},{},{},{},{}\n",
+                row.day,
+                row.phase,
+                row.stats.median.as_nanos(),
+                row.stats.min.as_nanos(),
+                row.stats.max.as_nanos()
+            ));
+        }
+        let total = self.total();
+        s.push_str(&format!(
+            "total,,{},{},{}\n",
+            total.median.as_nanos(),
+            total.min.as_nanos(),
+            total.max.as_nanos()
+        ));
+        s
+    }
+}
+
+/// Replace everything between the README markers with `table`,
+/// returns `None` if the markers are missing
+pub fn splice_readme(readme: &str, table: &str) -> Option<String> {
+    let start = readme.find(README_START)? + README_START.len();
+    let end = start + readme[start..].find(README_END)?;
+    Some(format!("{}\n{}{}", &readme[..start], table, &readme[end..]))
+}
+
+fn human(d: Duration) -> String {
+    let nanos = d.as_nanos() as f64;
+    if nanos < 1e3 {
+        format!("{} ns", nanos)
+    } else if nanos < 1e6 {
+        format!("{:.2} µs", nanos / 1e3)
+    } else if nanos < 1e9 {
+        format!("{:.2} ms", nanos / 1e6)
+    } else {
+        format!("{:.2} s", nanos / 1e9)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(ms: u64) -> Duration {
+        Duration::from_millis(ms)
+    }
+
+    #[test]
+    fn stats() {
+        assert_eq!(Stats::from_samples(&[]), None);
+        assert_eq!(
+            Stats::from_samples(&[ms(5), ms(1), ms(3)]),
+            Some(Stats {
+                median: ms(3),
+                min: ms(1),
+                max: ms(5)
+            })
+        );
+        assert_eq!(
+            Stats::from_samples(&[ms(4), ms(1), ms(2), ms(8)]).map(|s| s.median),
+            Some(ms(3))
+        );
+    }
+
+    #[test]
+    fn render() {
+        let mut report = Report::default();
+        let stats = Stats {
+            median: ms(2),
+            min: ms(1),
+            max: Duration::from_micros(2500),
+        };
+        report.push(1, Phase::Parse, stats);
+        report.push(1, Phase::PartB, stats);
+
+        assert_eq!(report.total().median, ms(4));
+        assert_eq!(
+            report.to_csv(),
+            "day,phase,median_ns,min_ns,max_ns\n\
+             1,parse,2000000,1000000,2500000\n\
+             1,part B,2000000,1000000,2500000\n\
+             total,,4000000,2000000,5000000\n"
+        );
+        assert!(report
+            .to_markdown()
+            .contains("| 1 | part B | 2.00 ms | 1.00 ms | 2.50 ms |\n"));
+        assert!(report
+            .to_markdown()
+            .ends_with("| **Total** | | **4.00 ms** | 2.00 ms | 5.00 ms |\n"));
+    }
+
+    #[test]
+    fn splice() {
+        let readme = format!("# Title\n{}\nold\n{}\nrest\n", README_START, README_END);
+        assert_eq!(
+            splice_readme(&readme, "new\n").unwrap(),
+            format!("# Title\n{}\nnew\n{}\nrest\n", README_START, README_END)
+        );
+        assert_eq!(splice_readme("# Title\n", "new\n"), None);
+    }
+}